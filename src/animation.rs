@@ -0,0 +1,287 @@
+use crate::geometry::{Angle, BasicPoint, Point3d, Vector3d};
+use crate::linalg::Quaternion;
+use crate::render::RGB;
+use crate::scene::NodeTransform;
+
+use core::f64::consts::PI;
+use std::time::Duration;
+
+
+pub trait Interpolate: Copy {
+    // `t` = 0 gives `self`, `t` = 1 gives `other`; eased values of `t` may leave that range
+    fn interpolate(self, other: Self, t: f64) -> Self;
+}
+
+impl Interpolate for f64 {
+    fn interpolate(self, other: f64, t: f64) -> f64 {
+        self + (other - self) * t
+    }
+}
+
+impl Interpolate for BasicPoint<f64> {
+    fn interpolate(self, other: BasicPoint<f64>, t: f64) -> BasicPoint<f64> {
+        self + (other - self) * t
+    }
+}
+
+impl Interpolate for Point3d {
+    fn interpolate(self, other: Point3d, t: f64) -> Point3d {
+        self + (other - self) * t
+    }
+}
+
+impl Interpolate for Vector3d {
+    fn interpolate(self, other: Vector3d, t: f64) -> Vector3d {
+        self.lerp(other, t)
+    }
+}
+
+// Angles are not wrapped, so that a track can spin through several full turns
+impl Interpolate for Angle {
+    fn interpolate(self, other: Angle, t: f64) -> Angle {
+        self + (other - self) * t
+    }
+}
+
+impl Interpolate for RGB {
+    fn interpolate(self, other: RGB, t: f64) -> RGB {
+        let channel = |a: u8, b: u8| (a as f64).interpolate(b as f64, t).round().clamp(0.0, 255.0) as u8;
+        RGB::new(channel(self.r, other.r), channel(self.g, other.g), channel(self.b, other.b))
+    }
+}
+
+impl Interpolate for Quaternion {
+    fn interpolate(self, other: Quaternion, t: f64) -> Quaternion {
+        self.slerp(other, t)
+    }
+}
+
+impl Interpolate for NodeTransform {
+    fn interpolate(self, other: NodeTransform, t: f64) -> NodeTransform {
+        NodeTransform {
+            translation: self.translation.interpolate(other.translation, t),
+            rotation: self.rotation.interpolate(other.rotation, t),
+            scale: self.scale.interpolate(other.scale, t),
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Easing {
+    Linear,
+    // Holds the start value until the next keyframe
+    Step,
+    QuadraticIn,
+    QuadraticOut,
+    QuadraticInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    SineInOut,
+    BackIn,
+    BackOut,
+    ElasticIn,
+    ElasticOut,
+    BounceOut,
+}
+
+impl Easing {
+    // Maps progress in [0, 1] to eased progress, which starts at 0 and ends at 1 but may overshoot in between
+    pub fn apply(self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::Step => if t < 1.0 { 0.0 } else { 1.0 },
+            Easing::QuadraticIn => t * t,
+            Easing::QuadraticOut => 1.0 - (1.0 - t).powi(2),
+            Easing::QuadraticInOut => if t < 0.5 { 2.0 * t * t } else { 1.0 - 2.0 * (1.0 - t).powi(2) },
+            Easing::CubicIn => t.powi(3),
+            Easing::CubicOut => 1.0 - (1.0 - t).powi(3),
+            Easing::CubicInOut => if t < 0.5 { 4.0 * t.powi(3) } else { 1.0 - 4.0 * (1.0 - t).powi(3) },
+            Easing::SineInOut => (1.0 - (PI * t).cos()) / 2.0,
+            Easing::BackIn => t * t * (2.70158 * t - 1.70158),
+            Easing::BackOut => 1.0 - Easing::BackIn.apply(1.0 - t),
+            Easing::ElasticIn => 1.0 - Easing::ElasticOut.apply(1.0 - t),
+            Easing::ElasticOut => {
+                if t == 0.0 || t == 1.0 {
+                    t
+                } else {
+                    2f64.powf(-10.0 * t) * ((t * 10.0 - 0.75) * 2.0 * PI / 3.0).sin() + 1.0
+                }
+            },
+            Easing::BounceOut => {
+                let bounce = |offset: f64, height: f64| 7.5625 * (t - offset).powi(2) + height;
+                if t < 1.0 / 2.75 {
+                    bounce(0.0, 0.0)
+                } else if t < 2.0 / 2.75 {
+                    bounce(1.5 / 2.75, 0.75)
+                } else if t < 2.5 / 2.75 {
+                    bounce(2.25 / 2.75, 0.9375)
+                } else {
+                    bounce(2.625 / 2.75, 0.984375)
+                }
+            },
+        }
+    }
+}
+
+
+// `easing` shapes the transition from this keyframe to the next one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Keyframe<T> {
+    pub time: Duration,
+    pub value: T,
+    pub easing: Easing,
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Track<T> {
+    keyframes: Vec<Keyframe<T>>,
+}
+
+impl<T: Interpolate> Track<T> {
+    pub fn new() -> Track<T> {
+        Track {keyframes: Vec::new()}
+    }
+
+    // Keyframes are kept sorted by time; one added at the time of an existing keyframe replaces it
+    pub fn with_keyframe(mut self, time: Duration, value: T, easing: Easing) -> Track<T> {
+        self.add_keyframe(time, value, easing);
+        self
+    }
+
+    pub fn add_keyframe(&mut self, time: Duration, value: T, easing: Easing) {
+        let keyframe = Keyframe {time, value, easing};
+        match self.keyframes.binary_search_by_key(&time, |keyframe| keyframe.time) {
+            Ok(index) => self.keyframes[index] = keyframe,
+            Err(index) => self.keyframes.insert(index, keyframe),
+        }
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe<T>] {
+        &self.keyframes
+    }
+
+    // The time of the last keyframe
+    pub fn duration(&self) -> Duration {
+        self.keyframes.last().map_or(Duration::ZERO, |keyframe| keyframe.time)
+    }
+
+    // Holds the first and the last value outside the keyframes; `None` for an empty track
+    pub fn sample(&self, time: Duration) -> Option<T> {
+        let next = self.keyframes.partition_point(|keyframe| keyframe.time <= time);
+        if next == 0 {
+            return self.keyframes.first().map(|keyframe| keyframe.value);
+        }
+        let previous = &self.keyframes[next - 1];
+        match self.keyframes.get(next) {
+            Some(next) => {
+                let progress = (time - previous.time).as_secs_f64() / (next.time - previous.time).as_secs_f64();
+                Some(previous.value.interpolate(next.value, previous.easing.apply(progress)))
+            },
+            None => Some(previous.value),
+        }
+    }
+}
+
+impl<T: Interpolate> Default for Track<T> {
+    fn default() -> Track<T> {
+        Track::new()
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Playback {
+    Once,
+    Loop,
+    // Plays forwards, then backwards, and so on
+    PingPong,
+}
+
+
+// A track played back in game time
+#[derive(Debug, Clone, PartialEq)]
+pub struct Animation<T> {
+    track: Track<T>,
+    playback: Playback,
+    elapsed: Duration,
+}
+
+impl<T: Interpolate> Animation<T> {
+    pub fn new(track: Track<T>, playback: Playback) -> Animation<T> {
+        Animation {track, playback, elapsed: Duration::ZERO}
+    }
+
+    pub fn track(&self) -> &Track<T> {
+        &self.track
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn advance(&mut self, time_delta: Duration) {
+        self.elapsed += time_delta;
+    }
+
+    pub fn seek(&mut self, elapsed: Duration) {
+        self.elapsed = elapsed;
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.playback == Playback::Once && self.elapsed >= self.track.duration()
+    }
+
+    pub fn value(&self) -> Option<T> {
+        self.track.sample(self.track_time())
+    }
+
+    fn track_time(&self) -> Duration {
+        let duration = self.track.duration().as_secs_f64();
+        if duration == 0.0 {
+            return Duration::ZERO;
+        }
+        let elapsed = self.elapsed.as_secs_f64();
+        let time = match self.playback {
+            Playback::Once => elapsed.min(duration),
+            Playback::Loop => elapsed % duration,
+            Playback::PingPong => duration - (elapsed % (2.0 * duration) - duration).abs(),
+        };
+        Duration::from_secs_f64(time)
+    }
+}
+
+
+// The last two states of a fixed-step simulation, blended to render frames that fall between steps
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interpolated<T> {
+    previous: T,
+    current: T,
+}
+
+impl<T: Interpolate> Interpolated<T> {
+    pub fn new(state: T) -> Interpolated<T> {
+        Interpolated {previous: state, current: state}
+    }
+
+    pub fn push(&mut self, state: T) {
+        self.previous = self.current;
+        self.current = state;
+    }
+
+    pub fn previous(&self) -> T {
+        self.previous
+    }
+
+    pub fn current(&self) -> T {
+        self.current
+    }
+
+    // `factor` = 0 gives the previous state, `factor` = 1 the current one
+    pub fn blend(&self, factor: f64) -> T {
+        self.previous.interpolate(self.current, factor)
+    }
+}
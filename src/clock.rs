@@ -1,51 +1,188 @@
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::hint;
+use std::rc::Rc;
 use std::time::{Duration, Instant};
 use std::thread;
 
 
-pub struct Clock {
-    last_tick: Instant,
+// Where clocks get the current time from and how they wait. Times are measured from an arbitrary fixed origin.
+pub trait TimeSource {
+    fn now(&self) -> Duration;
+    fn sleep(&self, duration: Duration);
+
+    // Busy-waits, which is more precise than sleeping but keeps a core busy
+    fn spin_until(&self, deadline: Duration) {
+        while self.now() < deadline {
+            hint::spin_loop();
+        }
+    }
 }
 
-impl Clock {
-    pub fn new() -> Clock {
-        let now = Instant::now();
-        Clock { last_tick: now }
+
+pub struct RealTime {
+    origin: Instant,
+}
+
+impl RealTime {
+    pub fn new() -> RealTime {
+        RealTime { origin: Instant::now() }
     }
+}
 
-    pub fn tick(&mut self, ticks_per_second: f64) -> Duration {
-        if ticks_per_second.is_infinite() || ticks_per_second <= 0.0 {
-            panic!("Invalid `ticks_per_second` ({:?}): must be finite and positive", ticks_per_second);
-        }
+impl TimeSource for RealTime {
+    fn now(&self) -> Duration {
+        self.origin.elapsed()
+    }
 
-        let interval = Duration::from_secs_f64(ticks_per_second.recip());
-        let wakeup_instant = self.last_tick + interval;
-        let now = Instant::now();
-        if wakeup_instant <= now {
-            let result = now - self.last_tick;
-            self.last_tick = now;
-            return result
-        }
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
+
+
+// Time that only moves when told to, for deterministic tests. Clones share the same time, so a test can keep one to
+// advance the time of a clock owning another; sleeping advances it at once.
+#[derive(Clone, Default)]
+pub struct ManualTime {
+    now: Rc<Cell<Duration>>,
+}
+
+impl ManualTime {
+    pub fn new() -> ManualTime {
+        ManualTime::default()
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.now.set(self.now.get() + duration);
+    }
+
+    pub fn set(&self, now: Duration) {
+        self.now.set(now);
+    }
+}
+
+impl TimeSource for ManualTime {
+    fn now(&self) -> Duration {
+        self.now.get()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
 
-        let sleep_duration = wakeup_instant - now;
-        thread::sleep(sleep_duration);
+    fn spin_until(&self, deadline: Duration) {
+        self.now.set(self.now.get().max(deadline));
+    }
+}
 
-        let after_sleep = Instant::now();
-        let result = after_sleep - self.last_tick;
-        self.last_tick = after_sleep;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameLimit {
+    Uncapped,
+    // Sleeping may overshoot by a good fraction of a millisecond, depending on the scheduler
+    Sleep,
+    // Sleeps until `spin` before the deadline, then busy-waits the rest of the way
+    Hybrid { spin: Duration },
+    // Presenting a frame waits for the display, so the limiter only measures frame times
+    VSync,
+}
+
+
+// Paces frames to a target rate. Deadlines follow each other at exact intervals rather than counting from when the
+// previous wait ended, so that a frame finishing late is made up for by a shorter wait for the next one. A limiter
+// falling more than a whole frame behind starts afresh instead of rushing through frames to catch up.
+pub struct FrameLimiter<Source: TimeSource = RealTime> {
+    source: Source,
+    strategy: FrameLimit,
+    interval: Duration,
+    deadline: Duration,
+    last_frame: Duration,
+}
+
+impl FrameLimiter {
+    pub fn new(frames_per_second: f64, strategy: FrameLimit) -> FrameLimiter {
+        FrameLimiter::with_source(RealTime::new(), frames_per_second, strategy)
+    }
+}
+
+impl<Source: TimeSource> FrameLimiter<Source> {
+    pub fn with_source(source: Source, frames_per_second: f64, strategy: FrameLimit) -> FrameLimiter<Source> {
+        let now = source.now();
+        let mut limiter = FrameLimiter { source, strategy, interval: Duration::ZERO, deadline: now, last_frame: now };
+        limiter.set_frames_per_second(frames_per_second);
+        limiter
+    }
+
+    pub fn strategy(&self) -> FrameLimit {
+        self.strategy
+    }
+
+    // The next frame is due one interval after the previous one
+    pub fn set_strategy(&mut self, strategy: FrameLimit) {
+        self.strategy = strategy;
+        self.deadline = self.last_frame + self.interval;
+    }
+
+    pub fn set_frames_per_second(&mut self, frames_per_second: f64) {
+        if !frames_per_second.is_finite() || frames_per_second <= 0.0 {
+            panic!("Invalid `frames_per_second` ({:?}): must be finite and positive", frames_per_second);
+        }
+        self.interval = Duration::from_secs_f64(frames_per_second.recip());
+        self.deadline = self.last_frame + self.interval;
+    }
+
+    // Waits until the next frame is due and returns the time since the previous call
+    pub fn wait(&mut self) -> Duration {
+        let now = self.source.now();
+        match self.strategy {
+            FrameLimit::Uncapped | FrameLimit::VSync => {},
+            FrameLimit::Sleep => {
+                if now < self.deadline {
+                    self.source.sleep(self.deadline - now);
+                }
+            },
+            FrameLimit::Hybrid {spin} => {
+                if now + spin < self.deadline {
+                    self.source.sleep(self.deadline - spin - now);
+                }
+                self.source.spin_until(self.deadline);
+            },
+        }
+
+        // Strategies which do not wait keep the deadline one interval ahead, so that it does not drift away from the
+        // actual frames and make a waiting strategy switched to later oversleep
+        let now = self.source.now();
+        self.deadline = match self.strategy {
+            FrameLimit::Uncapped | FrameLimit::VSync => now + self.interval,
+            FrameLimit::Sleep | FrameLimit::Hybrid {..} => (self.deadline + self.interval).min(now + self.interval),
+        };
+        if now > self.deadline {
+            self.deadline = now + self.interval;
+        }
+        let result = now - self.last_frame;
+        self.last_frame = now;
         result
     }
 }
 
 
-pub struct EventsPerSecondTracker {
-    last_reset: Instant,
+pub struct EventsPerSecondTracker<Source: TimeSource = RealTime> {
+    source: Source,
+    last_reset: Duration,
     events_since_last_reset: u64,
 }
 
 impl EventsPerSecondTracker {
     pub fn new() -> EventsPerSecondTracker {
-        EventsPerSecondTracker { last_reset: Instant::now(), events_since_last_reset: 0 }
+        EventsPerSecondTracker::with_source(RealTime::new())
+    }
+}
+
+impl<Source: TimeSource> EventsPerSecondTracker<Source> {
+    pub fn with_source(source: Source) -> EventsPerSecondTracker<Source> {
+        let now = source.now();
+        EventsPerSecondTracker { source, last_reset: now, events_since_last_reset: 0 }
     }
 
     pub fn event(&mut self) {
@@ -53,33 +190,539 @@ impl EventsPerSecondTracker {
     }
 
     pub fn mean(&self) -> f64 {
-        let now = Instant::now();
+        let now = self.source.now();
         (self.events_since_last_reset as f64) / (now - self.last_reset).as_secs_f64()
     }
 
     pub fn reset(&mut self) {
-        self.last_reset = Instant::now();
+        self.last_reset = self.source.now();
         self.events_since_last_reset = 0;
     }
 }
 
 
-pub struct ApproximateTimer {
-    interval: Duration,
-    remaining: Duration,
+// Frame times over a sliding window of the most recent frames
+pub struct FrameStats {
+    window: usize,
+    frame_times: VecDeque<Duration>,
 }
 
-impl ApproximateTimer {
-    pub fn new(interval: Duration) -> ApproximateTimer {
-        ApproximateTimer { interval, remaining: interval }
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameSummary {
+    pub min: Duration,
+    pub max: Duration,
+    pub mean: Duration,
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+}
+
+impl FrameStats {
+    pub fn new(window: usize) -> FrameStats {
+        if window == 0 {
+            panic!("Invalid `window`: must be positive");
+        }
+        FrameStats { window, frame_times: VecDeque::with_capacity(window) }
+    }
+
+    pub fn record(&mut self, frame_time: Duration) {
+        if self.frame_times.len() == self.window {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(frame_time);
+    }
+
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    // Oldest first
+    pub fn frame_times(&self) -> impl Iterator<Item = Duration> + '_ {
+        self.frame_times.iter().copied()
     }
 
-    pub fn update(&mut self, time_delta: Duration) -> u64 {
-        let difference_secs = self.remaining.as_secs_f64() - time_delta.as_secs_f64();
-        let interval_secs = self.interval.as_secs_f64();
-        let num_overruns = -difference_secs.div_euclid(interval_secs) as u64;
-        let new_remaining = Duration::from_secs_f64(difference_secs.rem_euclid(interval_secs));
-        self.remaining = new_remaining;
-        num_overruns
+    pub fn min(&self) -> Option<Duration> {
+        self.frame_times().min()
+    }
+
+    pub fn max(&self) -> Option<Duration> {
+        self.frame_times().max()
+    }
+
+    pub fn mean(&self) -> Option<Duration> {
+        let count = self.frame_times.len() as u32;
+        (count != 0).then(|| self.frame_times().sum::<Duration>() / count)
+    }
+
+    // Nearest-rank percentile, `percent` ranging from 0 to 100
+    pub fn percentile(&self, percent: f64) -> Option<Duration> {
+        self.percentiles(&[percent]).map(|values| values[0])
+    }
+
+    pub fn summary(&self) -> Option<FrameSummary> {
+        let percentiles = self.percentiles(&[50.0, 95.0, 99.0])?;
+        Some(FrameSummary {
+            min: self.min()?,
+            max: self.max()?,
+            mean: self.mean()?,
+            p50: percentiles[0],
+            p95: percentiles[1],
+            p99: percentiles[2],
+        })
+    }
+
+    // Counts of frame times in consecutive buckets of `bucket_width` starting at zero; the last bucket also counts
+    // everything longer
+    pub fn histogram(&self, bucket_width: Duration, bucket_count: usize) -> Vec<usize> {
+        let mut buckets = vec![0; bucket_count];
+        if bucket_count == 0 || bucket_width.is_zero() {
+            return buckets;
+        }
+        for frame_time in self.frame_times() {
+            let bucket = (frame_time.as_nanos() / bucket_width.as_nanos()) as usize;
+            buckets[bucket.min(bucket_count - 1)] += 1;
+        }
+        buckets
+    }
+
+    fn percentiles(&self, percents: &[f64]) -> Option<Vec<Duration>> {
+        if self.frame_times.is_empty() {
+            return None;
+        }
+        let mut sorted: Vec<Duration> = self.frame_times().collect();
+        sorted.sort_unstable();
+        let rank = |percent: f64| ((percent.clamp(0.0, 100.0) / 100.0 * sorted.len() as f64).ceil() as usize).max(1);
+        Some(percents.iter().map(|&percent| sorted[rank(percent) - 1]).collect())
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimerId(u64);
+
+
+struct Timer<Context> {
+    id: TimerId,
+    // The scheduler time of the next firing, or the time left until it while paused
+    due: Duration,
+    paused: bool,
+    interval: Option<Duration>,
+    callback: Box<dyn FnMut(&mut Context)>,
+}
+
+
+// Runs callbacks after a delay or at a fixed interval, in the time passed to `update`. Callbacks get the context passed
+// to `update`, through which they can reach application state. Timers due at the same time fire in the order they were
+// scheduled.
+pub struct Scheduler<Context = ()> {
+    now: Duration,
+    next_id: u64,
+    timers: Vec<Timer<Context>>,
+}
+
+impl<Context> Scheduler<Context> {
+    pub fn new() -> Scheduler<Context> {
+        Scheduler { now: Duration::ZERO, next_id: 0, timers: Vec::new() }
+    }
+
+    pub fn after(&mut self, delay: Duration, callback: impl FnMut(&mut Context) + 'static) -> TimerId {
+        self.add(delay, None, Box::new(callback))
+    }
+
+    // The first firing is one interval from now
+    pub fn every(&mut self, interval: Duration, callback: impl FnMut(&mut Context) + 'static) -> TimerId {
+        if interval.is_zero() {
+            panic!("Invalid `interval`: must be positive");
+        }
+        self.add(interval, Some(interval), Box::new(callback))
+    }
+
+    // Returns whether the timer was still scheduled; one-shot timers are removed once they fire
+    pub fn cancel(&mut self, id: TimerId) -> bool {
+        let count = self.timers.len();
+        self.timers.retain(|timer| timer.id != id);
+        self.timers.len() != count
+    }
+
+    pub fn is_scheduled(&self, id: TimerId) -> bool {
+        self.timers.iter().any(|timer| timer.id == id)
+    }
+
+    // The time left until the timer fires; paused timers keep what was left when they were paused
+    pub fn remaining(&self, id: TimerId) -> Option<Duration> {
+        let timer = self.timers.iter().find(|timer| timer.id == id)?;
+        Some(if timer.paused { timer.due } else { timer.due - self.now })
+    }
+
+    pub fn pause(&mut self, id: TimerId) -> bool {
+        let now = self.now;
+        self.timer_mut(id).map(|timer| {
+            if !timer.paused {
+                timer.paused = true;
+                timer.due -= now;
+            }
+        }).is_some()
+    }
+
+    pub fn resume(&mut self, id: TimerId) -> bool {
+        let now = self.now;
+        self.timer_mut(id).map(|timer| {
+            if timer.paused {
+                timer.paused = false;
+                timer.due += now;
+            }
+        }).is_some()
+    }
+
+    // Makes the timer fire `delay` from now; repeating timers keep their interval afterwards
+    pub fn reschedule(&mut self, id: TimerId, delay: Duration) -> bool {
+        let now = self.now;
+        self.timer_mut(id).map(|timer| timer.due = if timer.paused { delay } else { now + delay }).is_some()
+    }
+
+    pub fn update(&mut self, time_delta: Duration, context: &mut Context) {
+        self.now += time_delta;
+        loop {
+            let now = self.now;
+            let next = self
+                .timers
+                .iter()
+                .enumerate()
+                .filter(|(_, timer)| !timer.paused && timer.due <= now)
+                .min_by_key(|(_, timer)| timer.due)
+                .map(|(index, _)| index);
+            let index = match next {
+                Some(index) => index,
+                None => break,
+            };
+
+            let timer = &mut self.timers[index];
+            (timer.callback)(context);
+            match timer.interval {
+                // A repeating timer that fell behind fires once and skips the intervals it missed, keeping its phase
+                Some(interval) => {
+                    let behind = (now - timer.due).as_nanos() % interval.as_nanos();
+                    timer.due = now + interval - Duration::from_nanos(behind as u64);
+                },
+                None => {
+                    self.timers.remove(index);
+                },
+            }
+        }
+    }
+
+    fn add(&mut self, delay: Duration, interval: Option<Duration>, callback: Box<dyn FnMut(&mut Context)>) -> TimerId {
+        let id = TimerId(self.next_id);
+        self.next_id += 1;
+        self.timers.push(Timer { id, due: self.now + delay, paused: false, interval, callback });
+        id
+    }
+
+    fn timer_mut(&mut self, id: TimerId) -> Option<&mut Timer<Context>> {
+        self.timers.iter_mut().find(|timer| timer.id == id)
+    }
+}
+
+impl<Context> Default for Scheduler<Context> {
+    fn default() -> Scheduler<Context> {
+        Scheduler::new()
+    }
+}
+
+
+// Runs a simulation in fixed steps, however long rendered frames take. Frame time accumulates until whole steps are
+// due; at most `max_steps` are run per frame, and any backlog beyond that is dropped so that a slow simulation cannot
+// fall further and further behind.
+pub struct GameLoop {
+    step: Duration,
+    max_steps: u32,
+    accumulator: Duration,
+}
+
+impl GameLoop {
+    pub fn new(steps_per_second: f64, max_steps: u32) -> GameLoop {
+        if !steps_per_second.is_finite() || steps_per_second <= 0.0 {
+            panic!("Invalid `steps_per_second` ({:?}): must be finite and positive", steps_per_second);
+        }
+        let step = Duration::from_secs_f64(steps_per_second.recip());
+        if step.is_zero() {
+            panic!("Invalid `steps_per_second` ({:?}): steps must last at least a nanosecond", steps_per_second);
+        }
+        if max_steps == 0 {
+            panic!("Invalid `max_steps`: must be positive");
+        }
+        GameLoop { step, max_steps, accumulator: Duration::ZERO }
+    }
+
+    pub fn step(&self) -> Duration {
+        self.step
+    }
+
+    // Returns the number of steps due after `frame_time` more has passed
+    pub fn advance(&mut self, frame_time: Duration) -> u32 {
+        self.accumulator += frame_time;
+        let mut steps = 0;
+        while self.accumulator >= self.step {
+            if steps == self.max_steps {
+                self.accumulator = Duration::from_nanos((self.accumulator.as_nanos() % self.step.as_nanos()) as u64);
+                break;
+            }
+            self.accumulator -= self.step;
+            steps += 1;
+        }
+        steps
+    }
+
+    // How far into the next step the loop is, from 0 to 1: the weight of the latest simulation state when blending
+    // it with the one before for rendering
+    pub fn interpolation_factor(&self) -> f64 {
+        self.accumulator.as_secs_f64() / self.step.as_secs_f64()
+    }
+
+    // Calls `update` with the step duration once per due step and returns the interpolation factor
+    pub fn run_frame(&mut self, frame_time: Duration, mut update: impl FnMut(Duration)) -> f64 {
+        for _ in 0..self.advance(frame_time) {
+            update(self.step);
+        }
+        self.interpolation_factor()
+    }
+}
+
+
+// Game time, as seen by the simulation and animations, next to the real time driving the UI. It stands still while
+// paused, except for single steps, and otherwise runs at `scale` times real speed.
+pub struct GameClock {
+    elapsed: Duration,
+    scale: f64,
+    paused: bool,
+    pending_step: Option<Duration>,
+}
+
+impl GameClock {
+    pub fn new() -> GameClock {
+        GameClock { elapsed: Duration::ZERO, scale: 1.0, paused: false, pending_step: None }
+    }
+
+    // Returns the game time that has passed during `real_delta`
+    pub fn advance(&mut self, real_delta: Duration) -> Duration {
+        let delta = if self.paused {
+            self.pending_step.take().unwrap_or(Duration::ZERO)
+        } else {
+            real_delta.mul_f64(self.scale)
+        };
+        self.elapsed += delta;
+        delta
+    }
+
+    // While paused, makes the next `advance` pass exactly `duration`, unscaled; does nothing while running
+    pub fn step(&mut self, duration: Duration) {
+        if self.paused {
+            self.pending_step = Some(duration);
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    pub fn set_scale(&mut self, scale: f64) {
+        if !scale.is_finite() || scale < 0.0 {
+            panic!("Invalid time scale ({:?}): must be finite and non-negative", scale);
+        }
+        self.scale = scale;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+        self.pending_step = None;
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn sleeping_limiter_waits_for_the_deadline() {
+        let time = ManualTime::new();
+        let mut limiter = FrameLimiter::with_source(time.clone(), 100.0, FrameLimit::Sleep);
+        time.advance(ms(3));
+        assert_eq!(limiter.wait(), ms(10));
+        assert_eq!(time.now(), ms(10));
+    }
+
+    #[test]
+    fn late_frame_is_made_up_for_by_the_next_one() {
+        let time = ManualTime::new();
+        let mut limiter = FrameLimiter::with_source(time.clone(), 100.0, FrameLimit::Hybrid {spin: ms(2)});
+        time.advance(ms(12));
+        assert_eq!(limiter.wait(), ms(12));
+        time.advance(ms(1));
+        assert_eq!(limiter.wait(), ms(8));
+        assert_eq!(time.now(), ms(20));
+    }
+
+    #[test]
+    fn limiter_falling_far_behind_starts_afresh() {
+        let time = ManualTime::new();
+        let mut limiter = FrameLimiter::with_source(time.clone(), 100.0, FrameLimit::Sleep);
+        time.advance(ms(50));
+        assert_eq!(limiter.wait(), ms(50));
+        assert_eq!(limiter.wait(), ms(10));
+        assert_eq!(time.now(), ms(60));
+    }
+
+    #[test]
+    fn uncapped_limiter_does_not_wait() {
+        let time = ManualTime::new();
+        let mut limiter = FrameLimiter::with_source(time.clone(), 100.0, FrameLimit::Uncapped);
+        time.advance(ms(1));
+        assert_eq!(limiter.wait(), ms(1));
+        assert_eq!(time.now(), ms(1));
+    }
+
+    #[test]
+    fn scheduler_fires_one_shot_timers_once() {
+        let mut scheduler = Scheduler::new();
+        let id = scheduler.after(ms(10), |count: &mut u32| *count += 1);
+        let mut count = 0;
+        scheduler.update(ms(9), &mut count);
+        assert_eq!((count, scheduler.remaining(id)), (0, Some(ms(1))));
+        scheduler.update(ms(1), &mut count);
+        scheduler.update(ms(10), &mut count);
+        assert_eq!(count, 1);
+        assert!(!scheduler.is_scheduled(id));
+    }
+
+    #[test]
+    fn scheduler_fires_timers_in_order_of_due_time() {
+        let mut scheduler = Scheduler::new();
+        scheduler.after(ms(20), |order: &mut Vec<u32>| order.push(2));
+        scheduler.every(ms(15), |order: &mut Vec<u32>| order.push(1));
+        scheduler.after(ms(20), |order: &mut Vec<u32>| order.push(3));
+        let mut order = Vec::new();
+        scheduler.update(ms(20), &mut order);
+        scheduler.update(ms(10), &mut order);
+        assert_eq!(order, [1, 2, 3, 1]);
+    }
+
+    #[test]
+    fn repeating_timer_skips_missed_intervals_keeping_its_phase() {
+        let mut scheduler = Scheduler::new();
+        let id = scheduler.every(ms(10), |count: &mut u32| *count += 1);
+        let mut count = 0;
+        scheduler.update(ms(35), &mut count);
+        assert_eq!((count, scheduler.remaining(id)), (1, Some(ms(5))));
+    }
+
+    #[test]
+    fn short_repeating_timer_survives_a_long_stall() {
+        let mut scheduler = Scheduler::new();
+        let id = scheduler.every(Duration::from_micros(1), |count: &mut u32| *count += 1);
+        let mut count = 0;
+        scheduler.update(Duration::from_secs(24 * 3600), &mut count);
+        assert_eq!((count, scheduler.remaining(id)), (1, Some(Duration::from_micros(1))));
+    }
+
+    #[test]
+    fn paused_timer_keeps_its_remaining_time() {
+        let mut scheduler = Scheduler::new();
+        let id = scheduler.after(ms(10), |count: &mut u32| *count += 1);
+        let mut count = 0;
+        scheduler.update(ms(4), &mut count);
+        assert!(scheduler.pause(id));
+        scheduler.update(ms(100), &mut count);
+        assert_eq!((count, scheduler.remaining(id)), (0, Some(ms(6))));
+        assert!(scheduler.resume(id));
+        scheduler.update(ms(6), &mut count);
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn cancelled_and_rescheduled_timers() {
+        let mut scheduler = Scheduler::new();
+        let cancelled = scheduler.after(ms(10), |count: &mut u32| *count += 100);
+        let rescheduled = scheduler.every(ms(10), |count: &mut u32| *count += 1);
+        assert!(scheduler.cancel(cancelled));
+        assert!(!scheduler.cancel(cancelled));
+        assert!(scheduler.reschedule(rescheduled, ms(3)));
+        let mut count = 0;
+        scheduler.update(ms(3), &mut count);
+        assert_eq!((count, scheduler.remaining(rescheduled)), (1, Some(ms(10))));
+    }
+
+    #[test]
+    fn game_loop_runs_whole_steps_and_keeps_the_rest() {
+        let mut game_loop = GameLoop::new(100.0, 5);
+        let mut updates = Vec::new();
+        let factor = game_loop.run_frame(ms(25), |step| updates.push(step));
+        assert_eq!(updates, [ms(10), ms(10)]);
+        assert_eq!(factor, 0.5);
+        assert_eq!(game_loop.advance(ms(5)), 1);
+        assert_eq!(game_loop.interpolation_factor(), 0.0);
+    }
+
+    #[test]
+    fn game_loop_drops_backlog_beyond_max_steps() {
+        let mut game_loop = GameLoop::new(100.0, 3);
+        assert_eq!(game_loop.advance(ms(1004)), 3);
+        assert_eq!(game_loop.interpolation_factor(), 0.4);
+        assert_eq!(game_loop.advance(ms(6)), 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn game_loop_rejects_steps_shorter_than_a_nanosecond() {
+        GameLoop::new(1e10, 5);
+    }
+
+    #[test]
+    fn switching_from_uncapped_waits_one_interval_after_the_previous_frame() {
+        let time = ManualTime::new();
+        let mut limiter = FrameLimiter::with_source(time.clone(), 100.0, FrameLimit::Uncapped);
+        for _ in 0..10 {
+            time.advance(ms(1));
+            assert_eq!(limiter.wait(), ms(1));
+        }
+        limiter.set_strategy(FrameLimit::Sleep);
+        time.advance(ms(1));
+        assert_eq!(limiter.wait(), ms(10));
+        assert_eq!(time.now(), ms(20));
+    }
+
+    #[test]
+    fn changing_the_rate_moves_the_deadline() {
+        let time = ManualTime::new();
+        let mut limiter = FrameLimiter::with_source(time.clone(), 10.0, FrameLimit::Sleep);
+        limiter.set_frames_per_second(100.0);
+        assert_eq!(limiter.wait(), ms(10));
+    }
+
+    #[test]
+    fn tracker_counts_events_per_second_since_reset() {
+        let time = ManualTime::new();
+        let mut tracker = EventsPerSecondTracker::with_source(time.clone());
+        for _ in 0..30 {
+            tracker.event();
+        }
+        time.advance(ms(500));
+        assert_eq!(tracker.mean(), 60.0);
+        tracker.reset();
+        tracker.event();
+        time.advance(ms(250));
+        assert_eq!(tracker.mean(), 4.0);
     }
 }
@@ -0,0 +1,547 @@
+use crate::clock::FrameStats;
+use crate::geometry::{BasicPoint, BasicVector, Dot, Norm, Point};
+use crate::render::{RGB, FillRule, Rasterize, Rasterizer, SolidFill};
+
+use core::f64::consts::PI;
+use std::time::Duration;
+
+
+// Shapes are given in pixel coordinates, with pixel centres at integer points. Strokes are centred on the outline
+// and get round joins and caps.
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Paint {
+    Fill(RGB),
+    Stroke {color: RGB, width: f64},
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub min: BasicPoint<f64>,
+    pub max: BasicPoint<f64>,
+    pub paint: Paint,
+}
+
+impl Rasterize for Rect {
+    fn rasterize<'a>(&self, rasterizer: &mut Rasterizer<'a>) {
+        let (min, max) = (self.min, self.max);
+        match self.paint {
+            Paint::Fill(color) => fill_region(rasterizer, min, max, color, |_| true),
+            Paint::Stroke {color, width} => {
+                // Corners stay square, unlike those of a closed polyline
+                let half = width / 2.0;
+                let outer_min = BasicPoint {x: min.x - half, y: min.y - half};
+                let outer_max = BasicPoint {x: max.x + half, y: max.y + half};
+                fill_region(rasterizer, outer_min, outer_max, color, |point| {
+                    point.x < min.x + half || point.x > max.x - half || point.y < min.y + half || point.y > max.y - half
+                });
+            },
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RoundedRect {
+    pub min: BasicPoint<f64>,
+    pub max: BasicPoint<f64>,
+    pub radius: f64,
+    pub paint: Paint,
+}
+
+impl RoundedRect {
+    // The radius is limited to half of the shorter side; inverted rects and NaNs give square corners
+    fn clamped_radius(&self) -> f64 {
+        let half_side = ((self.max.x - self.min.x).min(self.max.y - self.min.y) / 2.0).max(0.0);
+        self.radius.max(0.0).min(half_side)
+    }
+
+    pub fn outline(&self, tolerance: f64) -> Vec<BasicPoint<f64>> {
+        let radius = self.clamped_radius();
+        let centers = [
+            (BasicPoint {x: self.max.x - radius, y: self.min.y + radius}, -PI / 2.0),
+            (BasicPoint {x: self.max.x - radius, y: self.max.y - radius}, 0.0),
+            (BasicPoint {x: self.min.x + radius, y: self.max.y - radius}, PI / 2.0),
+            (BasicPoint {x: self.min.x + radius, y: self.min.y + radius}, PI),
+        ];
+        let steps = (arc_segments(radius, tolerance) / 4).max(1);
+        let mut points = Vec::with_capacity(4 * (steps + 1));
+        for &(center, start_angle) in &centers {
+            for step in 0..=steps {
+                let angle = start_angle + step as f64 / steps as f64 * PI / 2.0;
+                points.push(BasicPoint {x: center.x + radius * angle.cos(), y: center.y + radius * angle.sin()});
+            }
+        }
+        points
+    }
+}
+
+impl Rasterize for RoundedRect {
+    fn rasterize<'a>(&self, rasterizer: &mut Rasterizer<'a>) {
+        match self.paint {
+            Paint::Fill(color) => {
+                let radius = self.clamped_radius();
+                let inner_min = BasicPoint {x: self.min.x + radius, y: self.min.y + radius};
+                let inner_max = BasicPoint {x: self.max.x - radius, y: self.max.y - radius};
+                fill_region(rasterizer, self.min, self.max, color, |point| {
+                    let nearest = BasicPoint {
+                        x: point.x.max(inner_min.x).min(inner_max.x),
+                        y: point.y.max(inner_min.y).min(inner_max.y),
+                    };
+                    (point - nearest).norm() <= radius
+                });
+            },
+            Paint::Stroke {color, width} => stroke_path(rasterizer, &self.outline(0.25), true, width, color),
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ellipse {
+    pub center: BasicPoint<f64>,
+    pub radius_x: f64,
+    pub radius_y: f64,
+    pub paint: Paint,
+}
+
+impl Ellipse {
+    pub fn circle(center: BasicPoint<f64>, radius: f64, paint: Paint) -> Ellipse {
+        Ellipse {center, radius_x: radius, radius_y: radius, paint}
+    }
+
+    pub fn outline(&self, tolerance: f64) -> Vec<BasicPoint<f64>> {
+        let segments = arc_segments(self.radius_x.max(self.radius_y), tolerance);
+        let center = self.center;
+        (0..segments)
+            .map(|segment| {
+                let angle = segment as f64 / segments as f64 * 2.0 * PI;
+                BasicPoint {x: center.x + self.radius_x * angle.cos(), y: center.y + self.radius_y * angle.sin()}
+            })
+            .collect()
+    }
+}
+
+impl Rasterize for Ellipse {
+    fn rasterize<'a>(&self, rasterizer: &mut Rasterizer<'a>) {
+        match self.paint {
+            Paint::Fill(color) => {
+                let (center, radius_x, radius_y) = (self.center, self.radius_x, self.radius_y);
+                let min = BasicPoint {x: center.x - radius_x, y: center.y - radius_y};
+                let max = BasicPoint {x: center.x + radius_x, y: center.y + radius_y};
+                fill_region(rasterizer, min, max, color, |point| {
+                    ((point.x - center.x) / radius_x).powi(2) + ((point.y - center.y) / radius_y).powi(2) <= 1.0
+                });
+            },
+            Paint::Stroke {color, width} => stroke_path(rasterizer, &self.outline(0.25), true, width, color),
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polyline {
+    pub points: Vec<BasicPoint<f64>>,
+    pub closed: bool,
+    pub width: f64,
+    pub color: RGB,
+}
+
+impl Rasterize for Polyline {
+    fn rasterize<'a>(&self, rasterizer: &mut Rasterizer<'a>) {
+        stroke_path(rasterizer, &self.points, self.closed, self.width, self.color);
+    }
+}
+
+
+// Filled by triangulation at whole pixels, so it must stay simple once rounded; otherwise it is not drawn
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polygon {
+    pub outer: Vec<BasicPoint<f64>>,
+    pub holes: Vec<Vec<BasicPoint<f64>>>,
+    pub paint: Paint,
+}
+
+impl Rasterize for Polygon {
+    fn rasterize<'a>(&self, rasterizer: &mut Rasterizer<'a>) {
+        match self.paint {
+            Paint::Fill(color) => {
+                let round = |point: &BasicPoint<f64>| Point {x: point.x.round() as i32, y: point.y.round() as i32};
+                let outer: Vec<Point> = self.outer.iter().map(round).collect();
+                let holes: Vec<Vec<Point>> = self.holes.iter().map(|hole| hole.iter().map(round).collect()).collect();
+                rasterizer.fill_polygon(&outer, &holes, &mut SolidFill(color));
+            },
+            Paint::Stroke {color, width} => {
+                for ring in Some(&self.outer).into_iter().chain(&self.holes) {
+                    stroke_path(rasterizer, ring, true, width, color);
+                }
+            },
+        }
+    }
+}
+
+
+// Recent frame times as bars, the newest on the right, scaled so that `scale` reaches the top. Bars are green within
+// the 60 FPS budget and yellow within the 30 FPS one, which are marked by lines, and red beyond.
+pub struct FrameGraph<'a> {
+    pub stats: &'a FrameStats,
+    pub min: BasicPoint<f64>,
+    pub max: BasicPoint<f64>,
+    pub scale: Duration,
+}
+
+impl Rasterize for FrameGraph<'_> {
+    fn rasterize<'a>(&self, rasterizer: &mut Rasterizer<'a>) {
+        let (min, max) = (self.min, self.max);
+        Rect {min, max, paint: Paint::Fill(RGB::new(32, 32, 32))}.rasterize(rasterizer);
+
+        let budget_60 = Duration::from_secs(1) / 60;
+        let budget_30 = Duration::from_secs(1) / 30;
+        let height_of = |frame_time: Duration| {
+            (frame_time.as_secs_f64() / self.scale.as_secs_f64()).min(1.0) * (max.y - min.y)
+        };
+        let bar_width = (max.x - min.x) / self.stats.window() as f64;
+        let skipped = self.stats.window() - self.stats.frame_times().count();
+        for (index, frame_time) in self.stats.frame_times().enumerate() {
+            let color = if frame_time <= budget_60 {
+                RGB::new(0, 200, 0)
+            } else if frame_time <= budget_30 {
+                RGB::new(220, 200, 0)
+            } else {
+                RGB::new(220, 0, 0)
+            };
+            let left = min.x + (skipped + index) as f64 * bar_width;
+            let bar_min = BasicPoint {x: left, y: max.y - height_of(frame_time)};
+            let bar_max = BasicPoint {x: left + bar_width, y: max.y};
+            Rect {min: bar_min, max: bar_max, paint: Paint::Fill(color)}.rasterize(rasterizer);
+        }
+
+        for budget in [budget_60, budget_30] {
+            if budget < self.scale {
+                let y = max.y - height_of(budget);
+                let points = vec![BasicPoint {x: min.x, y}, BasicPoint {x: max.x, y}];
+                Polyline {points, closed: false, width: 1.0, color: RGB::new(160, 160, 160)}.rasterize(rasterizer);
+            }
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuadraticBezier {
+    pub start: BasicPoint<f64>,
+    pub control: BasicPoint<f64>,
+    pub end: BasicPoint<f64>,
+    pub width: f64,
+    pub color: RGB,
+}
+
+impl QuadraticBezier {
+    pub fn point_at(&self, t: f64) -> BasicPoint<f64> {
+        quadratic_point(self.start, self.control, self.end, t)
+    }
+
+    pub fn flatten(&self, tolerance: f64) -> Vec<BasicPoint<f64>> {
+        flatten_quadratic(self.start, self.control, self.end, tolerance)
+    }
+}
+
+impl Rasterize for QuadraticBezier {
+    fn rasterize<'a>(&self, rasterizer: &mut Rasterizer<'a>) {
+        stroke_path(rasterizer, &self.flatten(0.25), false, self.width, self.color);
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CubicBezier {
+    pub start: BasicPoint<f64>,
+    pub control1: BasicPoint<f64>,
+    pub control2: BasicPoint<f64>,
+    pub end: BasicPoint<f64>,
+    pub width: f64,
+    pub color: RGB,
+}
+
+impl CubicBezier {
+    pub fn point_at(&self, t: f64) -> BasicPoint<f64> {
+        cubic_point(self.start, self.control1, self.control2, self.end, t)
+    }
+
+    pub fn flatten(&self, tolerance: f64) -> Vec<BasicPoint<f64>> {
+        flatten_cubic(self.start, self.control1, self.control2, self.end, tolerance)
+    }
+}
+
+impl Rasterize for CubicBezier {
+    fn rasterize<'a>(&self, rasterizer: &mut Rasterizer<'a>) {
+        stroke_path(rasterizer, &self.flatten(0.25), false, self.width, self.color);
+    }
+}
+
+
+// A filled outline made of closed subpaths, e.g. a flattened SVG path
+#[derive(Debug, Clone, PartialEq)]
+pub struct Path {
+    pub subpaths: Vec<Vec<BasicPoint<f64>>>,
+    pub rule: FillRule,
+    pub color: RGB,
+}
+
+impl Rasterize for Path {
+    fn rasterize<'a>(&self, rasterizer: &mut Rasterizer<'a>) {
+        rasterizer.fill_path(&self.subpaths, self.rule, &mut SolidFill(self.color));
+    }
+}
+
+
+fn quadratic_point(a: BasicPoint<f64>, b: BasicPoint<f64>, c: BasicPoint<f64>, t: f64) -> BasicPoint<f64> {
+    let s = 1.0 - t;
+    BasicPoint {
+        x: s * s * a.x + 2.0 * s * t * b.x + t * t * c.x,
+        y: s * s * a.y + 2.0 * s * t * b.y + t * t * c.y,
+    }
+}
+
+
+fn cubic_point(
+    a: BasicPoint<f64>,
+    b: BasicPoint<f64>,
+    c: BasicPoint<f64>,
+    d: BasicPoint<f64>,
+    t: f64
+) -> BasicPoint<f64> {
+    let s = 1.0 - t;
+    let (k0, k1, k2, k3) = (s * s * s, 3.0 * s * s * t, 3.0 * s * t * t, t * t * t);
+    BasicPoint {
+        x: k0 * a.x + k1 * b.x + k2 * c.x + k3 * d.x,
+        y: k0 * a.y + k1 * b.y + k2 * c.y + k3 * d.y,
+    }
+}
+
+
+// Points on the curve no further than `tolerance` from it when connected (Wang's formula)
+pub fn flatten_quadratic(
+    start: BasicPoint<f64>,
+    control: BasicPoint<f64>,
+    end: BasicPoint<f64>,
+    tolerance: f64
+) -> Vec<BasicPoint<f64>> {
+    let bound = 0.25 * second_difference(start, control, end).norm();
+    flatten_curve(bound, tolerance, |t| quadratic_point(start, control, end, t))
+}
+
+
+// Points on the curve no further than `tolerance` from it when connected (Wang's formula)
+pub fn flatten_cubic(
+    start: BasicPoint<f64>,
+    control1: BasicPoint<f64>,
+    control2: BasicPoint<f64>,
+    end: BasicPoint<f64>,
+    tolerance: f64
+) -> Vec<BasicPoint<f64>> {
+    let second_difference = second_difference(start, control1, control2)
+        .norm()
+        .max(second_difference(control1, control2, end).norm());
+    flatten_curve(0.75 * second_difference, tolerance, |t| cubic_point(start, control1, control2, end, t))
+}
+
+
+fn second_difference(a: BasicPoint<f64>, b: BasicPoint<f64>, c: BasicPoint<f64>) -> BasicVector<f64> {
+    BasicVector {x: a.x - 2.0 * b.x + c.x, y: a.y - 2.0 * b.y + c.y}
+}
+
+
+// `bound` is the degree-dependent factor of Wang's formula times the largest second difference of control points
+fn flatten_curve(bound: f64, tolerance: f64, point_at: impl Fn(f64) -> BasicPoint<f64>) -> Vec<BasicPoint<f64>> {
+    let segments = ((bound / tolerance).sqrt().ceil() as usize).clamp(1, 1024);
+    (0..=segments).map(|segment| point_at(segment as f64 / segments as f64)).collect()
+}
+
+
+// The number of chords approximating a full circle with a sagitta of at most `tolerance`
+fn arc_segments(radius: f64, tolerance: f64) -> usize {
+    if radius <= tolerance {
+        return 8;
+    }
+    let step = 2.0 * (1.0 - tolerance / radius).acos();
+    ((2.0 * PI / step).ceil() as usize).clamp(8, 1024)
+}
+
+
+// Sets pixels of the box between `min` and `max` (clipped to the rasterizer) that the region contains
+fn fill_region(
+    rasterizer: &mut Rasterizer<'_>,
+    min: BasicPoint<f64>,
+    max: BasicPoint<f64>,
+    color: RGB,
+    contains: impl Fn(BasicPoint<f64>) -> bool
+) {
+    let clip = |value: f64, size: u32| value.clamp(-1.0, size as f64) as i64;
+    let (x_start, x_end) = (clip(min.x.ceil(), rasterizer.width()), clip(max.x.floor(), rasterizer.width()));
+    let (y_start, y_end) = (clip(min.y.ceil(), rasterizer.height()), clip(max.y.floor(), rasterizer.height()));
+    for y in y_start.max(0)..=y_end.min(rasterizer.height() as i64 - 1) {
+        for x in x_start.max(0)..=x_end.min(rasterizer.width() as i64 - 1) {
+            if contains(BasicPoint {x: x as f64, y: y as f64}) {
+                rasterizer.set(x as u32, y as u32, color);
+            }
+        }
+    }
+}
+
+
+fn stroke_path(rasterizer: &mut Rasterizer<'_>, points: &[BasicPoint<f64>], closed: bool, width: f64, color: RGB) {
+    if let [point] = points {
+        stroke_segment(rasterizer, *point, *point, width, color);
+    }
+    for pair in points.windows(2) {
+        stroke_segment(rasterizer, pair[0], pair[1], width, color);
+    }
+    if closed && points.len() > 2 {
+        stroke_segment(rasterizer, points[points.len() - 1], points[0], width, color);
+    }
+}
+
+
+// Sets the pixels within `width / 2` of the segment, i.e. a capsule around it
+fn stroke_segment(rasterizer: &mut Rasterizer<'_>, a: BasicPoint<f64>, b: BasicPoint<f64>, width: f64, color: RGB) {
+    // Hairlines still cover a pixel-wide band
+    let half = (width / 2.0).max(0.5);
+    let min = BasicPoint {x: a.x.min(b.x) - half, y: a.y.min(b.y) - half};
+    let max = BasicPoint {x: a.x.max(b.x) + half, y: a.y.max(b.y) + half};
+    let direction = b - a;
+    let length_squared = direction.dot(&direction);
+    fill_region(rasterizer, min, max, color, |point| {
+        let t = if length_squared == 0.0 {
+            0.0
+        } else {
+            ((point - a).dot(&direction) / length_squared).clamp(0.0, 1.0)
+        };
+        let nearest = a + direction * t;
+        (point - nearest).norm() <= half
+    });
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WHITE: RGB = RGB {r: 255, g: 255, b: 255};
+
+    // Rasterizes the shape into a small buffer and returns which pixels were set, row by row
+    fn draw(width: u32, height: u32, shape: &impl Rasterize) -> Vec<String> {
+        let mut data = vec![0; (width * height * 4) as usize];
+        let mut rasterizer = Rasterizer::new(&mut data, width, height);
+        shape.rasterize(&mut rasterizer);
+        data.chunks(width as usize * 4)
+            .map(|row| row.chunks(4).map(|pixel| if pixel[2] == 255 { '#' } else { '.' }).collect())
+            .collect()
+    }
+
+    fn point(x: f64, y: f64) -> BasicPoint<f64> {
+        BasicPoint {x, y}
+    }
+
+    fn is_set(rows: &[String], x: usize, y: usize) -> bool {
+        rows[y].as_bytes()[x] == b'#'
+    }
+
+    #[test]
+    fn ellipse_fills_pixel_centres_inside_it() {
+        let ellipse = Ellipse {center: point(4.0, 2.0), radius_x: 4.0, radius_y: 2.0, paint: Paint::Fill(WHITE)};
+        assert_eq!(draw(9, 5, &ellipse), ["....#....", ".#######.", "#########", ".#######.", "....#...."]);
+    }
+
+    #[test]
+    fn stroked_circle_leaves_its_inside_empty() {
+        let circle = Ellipse::circle(point(5.0, 5.0), 4.0, Paint::Stroke {color: WHITE, width: 1.0});
+        let rows = draw(11, 11, &circle);
+        for &(x, y) in &[(5, 1), (9, 5), (5, 9), (1, 5)] {
+            assert!(is_set(&rows, x, y), "({}, {}) is not set", x, y);
+        }
+        assert!(!is_set(&rows, 5, 5));
+        assert!(!is_set(&rows, 0, 0));
+    }
+
+    #[test]
+    fn stroked_rect_has_square_corners() {
+        let rect = Rect {min: point(2.0, 2.0), max: point(6.0, 6.0), paint: Paint::Stroke {color: WHITE, width: 2.0}};
+        assert_eq!(draw(9, 9, &rect), [
+            ".........",
+            ".#######.",
+            ".#######.",
+            ".##...##.",
+            ".##...##.",
+            ".##...##.",
+            ".#######.",
+            ".#######.",
+            ".........",
+        ]);
+    }
+
+    #[test]
+    fn rounded_rect_cuts_its_corners() {
+        let rect = RoundedRect {min: point(0.0, 0.0), max: point(6.0, 6.0), radius: 3.0, paint: Paint::Fill(WHITE)};
+        let rows = draw(7, 7, &rect);
+        assert!(!is_set(&rows, 0, 0));
+        assert!(!is_set(&rows, 6, 6));
+        assert!(is_set(&rows, 3, 0));
+        assert!(is_set(&rows, 3, 3));
+    }
+
+    #[test]
+    fn rounded_rect_radius_is_limited_by_its_sides() {
+        let rect = |radius| RoundedRect {min: point(0.0, 0.0), max: point(6.0, 6.0), radius, paint: Paint::Fill(WHITE)};
+        assert_eq!(draw(7, 7, &rect(100.0)), draw(7, 7, &rect(3.0)));
+        let square = Rect {min: point(0.0, 0.0), max: point(6.0, 6.0), paint: Paint::Fill(WHITE)};
+        assert_eq!(draw(7, 7, &rect(-1.0)), draw(7, 7, &square));
+        assert_eq!(draw(7, 7, &rect(f64::NAN)), draw(7, 7, &square));
+    }
+
+    #[test]
+    fn inverted_or_nan_rounded_rect_does_not_panic() {
+        for &paint in &[Paint::Fill(WHITE), Paint::Stroke {color: WHITE, width: 1.0}] {
+            let rect = RoundedRect {min: point(5.0, 5.0), max: point(1.0, 1.0), radius: 2.0, paint};
+            assert_eq!(rect.clamped_radius(), 0.0);
+            // Only checks that it does not panic
+            draw(7, 7, &RoundedRect {min: point(f64::NAN, 0.0), max: point(4.0, 4.0), radius: 2.0, paint});
+        }
+        let rect = RoundedRect {min: point(5.0, 5.0), max: point(1.0, 1.0), radius: 2.0, paint: Paint::Fill(WHITE)};
+        assert_eq!(draw(7, 7, &rect), ["......."; 7]);
+    }
+
+    #[test]
+    fn quadratic_bezier_passes_through_its_ends_and_bends_towards_its_control() {
+        let (start, control, end) = (point(0.0, 5.0), point(5.0, -5.0), point(10.0, 5.0));
+        let curve = QuadraticBezier {start, control, end, width: 1.0, color: WHITE};
+        assert_eq!(curve.point_at(0.5), point(5.0, 0.0));
+        let points = curve.flatten(0.25);
+        assert_eq!((points[0], points[points.len() - 1]), (curve.start, curve.end));
+
+        let rows = draw(11, 7, &curve);
+        assert!(is_set(&rows, 0, 5) && is_set(&rows, 5, 0) && is_set(&rows, 10, 5));
+        assert!(!is_set(&rows, 5, 5));
+    }
+
+    #[test]
+    fn cubic_bezier_passes_through_its_ends_and_bends_towards_its_controls() {
+        let curve = CubicBezier {
+            start: point(0.0, 8.0),
+            control1: point(0.0, 0.0),
+            control2: point(10.0, 0.0),
+            end: point(10.0, 8.0),
+            width: 1.0,
+            color: WHITE,
+        };
+        assert_eq!(curve.point_at(0.5), point(5.0, 2.0));
+        let points = curve.flatten(0.25);
+        assert_eq!((points[0], points[points.len() - 1]), (curve.start, curve.end));
+
+        let rows = draw(11, 9, &curve);
+        assert!(is_set(&rows, 0, 8) && is_set(&rows, 5, 2) && is_set(&rows, 10, 8));
+        assert!(!is_set(&rows, 5, 8));
+    }
+}
@@ -1,6 +1,7 @@
 use crate::linalg::Matrix2d;
 
-use gcd::Gcd;
+use std::cmp::Ordering;
+use std::convert::TryFrom;
 use std::ops::{Sub, Add, Mul, Div, Neg};
 use std::mem;
 use std::fmt::Debug;
@@ -15,6 +16,15 @@ pub struct BasicPoint<T> {
 
 pub type Point = BasicPoint<i32>;
 
+impl<T> BasicPoint<T> {
+    pub fn map<P>(self, func: &impl Fn(T) -> P) -> BasicPoint<P> {
+        BasicPoint {
+            x: func(self.x),
+            y: func(self.y),
+        }
+    }
+}
+
 impl<T> From<(T, T)> for BasicPoint<T> {
     fn from(tuple: (T, T)) -> BasicPoint<T> {
         BasicPoint {x: tuple.0, y: tuple.1}
@@ -97,6 +107,40 @@ impl<O, T: Neg<Output = O>> Neg for BasicVector<T> {
     }
 }
 
+impl<O, S: Copy, T: Mul<S, Output = O>> Mul<S> for BasicVector<T> {
+    type Output = BasicVector<O>;
+
+    fn mul(self, scalar: S) -> BasicVector<O> {
+        BasicVector { x: self.x * scalar, y: self.y * scalar }
+    }
+}
+
+impl<O, S: Copy, T: Div<S, Output = O>> Div<S> for BasicVector<T> {
+    type Output = BasicVector<O>;
+
+    fn div(self, scalar: S) -> BasicVector<O> {
+        BasicVector { x: self.x / scalar, y: self.y / scalar }
+    }
+}
+
+impl<T: Copy + Add<Output = T> + Sub<Output = T> + Mul<Output = T> + Div<Output = T>> BasicVector<T> {
+    // `t` = 0 gives `self`, `t` = 1 gives `other`
+    pub fn lerp(self, other: BasicVector<T>, t: T) -> BasicVector<T> {
+        self + (other - self) * t
+    }
+
+    // `onto` must not be a zero vector
+    pub fn project_onto(self, onto: BasicVector<T>) -> BasicVector<T> {
+        onto * (self.dot(&onto) / onto.dot(&onto))
+    }
+
+    // Mirrors the vector across the line perpendicular to `normal`, which need not be normalised
+    pub fn reflect(self, normal: BasicVector<T>) -> BasicVector<T> {
+        let projected = self.project_onto(normal);
+        self - (projected + projected)
+    }
+}
+
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct BasicTriangle<P> {
@@ -126,11 +170,12 @@ impl<P: PartialEq + LiesOn> BasicTriangle<P> {
 pub type Triangle = BasicTriangle<Point>;
 pub type Triangle3d = BasicTriangle<Point3d>;
 
+// Coefficients of `a * x + b * y + c = 0`; they are wide enough to hold exact values for any `i32` points
 #[derive(Debug, Clone, Copy)]
 pub struct Line {
-    a: i32,
-    b: i32,
-    c: i32,
+    a: i64,
+    b: i64,
+    c: i128,
 }
 
 impl Line {
@@ -139,45 +184,86 @@ impl Line {
             panic!("More than one line passes through two coinciding points");
         }
 
-        if p.x == q.x {
-            Line { a: 1, b: 0, c: -p.x }
+        let (px, py, qx, qy) = (p.x as i64, p.y as i64, q.x as i64, q.y as i64);
+        if px == qx {
+            Line { a: 1, b: 0, c: -px as i128 }
         } else {
-            Line { a: p.y - q.y, b: q.x - p.x, c: p.x * q.y - p.y * q.x }
+            Line { a: py - qy, b: qx - px, c: (px * qy) as i128 - (py * qx) as i128 }
         }
     }
 
     pub fn horizontal(y: i32) -> Line {
-        Line { a: 0, b: 1, c: -y }
+        Line { a: 0, b: 1, c: -y as i128 }
+    }
+
+    // The exact intersection point, or `None` for parallel or coinciding lines
+    pub fn intersect_rational(self, other: Line) -> Option<RationalPoint> {
+        let (a1, b1, c1) = (self.a as i128, self.b as i128, self.c);
+        let (a2, b2, c2) = (other.a as i128, other.b as i128, other.c);
+        let denominator = b1 * a2 - a1 * b2;
+        if denominator == 0 {
+            return None;
+        }
+        let sign = denominator.signum();
+        Some(RationalPoint {
+            x: (c1 * b2 - b1 * c2) * sign,
+            y: (a1 * c2 - c1 * a2) * sign,
+            denominator: denominator * sign,
+        })
     }
 
-    pub fn intersect(self, other: Line) -> Point {
-        let x_numerator = self.c * other.b - self.b * other.c;
-        let y_numerator = self.a * other.c - self.c * other.a;
-        let denominator = self.b * other.a - self.a * other.b;
-        Point { x: x_numerator / denominator, y: y_numerator / denominator }
+    // Coordinates are truncated towards zero; `None` also when the point does not fit into `Point`
+    pub fn intersect(self, other: Line) -> Option<Point> {
+        self.intersect_rational(other).and_then(RationalPoint::truncated)
+    }
+
+    // Like `intersect`, but coordinates are rounded to the nearest integer
+    pub fn intersect_rounded(self, other: Line) -> Option<Point> {
+        self.intersect_rational(other).and_then(RationalPoint::rounded)
     }
 
     pub fn contains_point(self, point: Point) -> bool {
-        self.a * point.x + self.b * point.y + self.c == 0
+        (self.a * point.x as i64) as i128 + (self.b * point.y as i64) as i128 + self.c == 0
     }
 }
 
 impl PartialEq for Line {
+    // Lines coincide when their coefficient triples are proportional, i.e. all 2x2 minors vanish
     fn eq(&self, other: &Line) -> bool {
-        let ga = (self.a.abs() as u32).gcd(other.a.abs() as u32) as i32;
-        let p = (self.a / ga) as i64;
-        let q = (other.a / ga) as i64;
-        
-        let  first = self.a as i64 * q == other.a as i64 * p;
-        let second = self.b as i64 * q == other.b as i64 * p;
-        let  third = self.c as i64 * q == other.c as i64 * p;
-        first && second && third
+        let (a1, b1, c1) = (self.a as i128, self.b as i128, self.c);
+        let (a2, b2, c2) = (other.a as i128, other.b as i128, other.c);
+        a1 * b2 == a2 * b1 && a1 * c2 == a2 * c1 && b1 * c2 == b2 * c1
     }
 }
 
 impl Eq for Line {}
 
 
+// The point (x / denominator, y / denominator); the denominator is always positive
+#[derive(Debug, Clone, Copy)]
+pub struct RationalPoint {
+    pub x: i128,
+    pub y: i128,
+    pub denominator: i128,
+}
+
+impl RationalPoint {
+    pub fn truncated(self) -> Option<Point> {
+        RationalPoint::to_point(self.x / self.denominator, self.y / self.denominator)
+    }
+
+    // Halves are rounded away from zero
+    pub fn rounded(self) -> Option<Point> {
+        let round = |value: i128| (2 * value + value.signum() * self.denominator) / (2 * self.denominator);
+        RationalPoint::to_point(round(self.x), round(self.y))
+    }
+
+    fn to_point(x: i128, y: i128) -> Option<Point> {
+        Some(Point { x: i32::try_from(x).ok()?, y: i32::try_from(y).ok()? })
+    }
+}
+
+
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub struct HorizontalSegment {
     left: Point,
@@ -190,7 +276,7 @@ impl HorizontalSegment {
         if left.x > right.x {
             mem::swap(&mut left, &mut right);
         }
-        HorizontalSegment { left, width: (right.x - left.x) as u32 }
+        HorizontalSegment { left, width: (right.x as i64 - left.x as i64) as u32 }
     }
 
     pub fn left(self) -> Point {
@@ -198,7 +284,7 @@ impl HorizontalSegment {
     }
 
     pub fn right(self) -> Point {
-        Point { x: self.left.x + (self.width as i32), y: self.left.y }
+        Point { x: (self.left.x as i64 + self.width as i64) as i32, y: self.left.y }
     }
 
     pub fn y(self) -> i32 {
@@ -346,6 +432,40 @@ impl<O, T: Neg<Output = O>> Neg for BasicVector3d<T> {
     }
 }
 
+impl<O, S: Copy, T: Mul<S, Output = O>> Mul<S> for BasicVector3d<T> {
+    type Output = BasicVector3d<O>;
+
+    fn mul(self, scalar: S) -> BasicVector3d<O> {
+        BasicVector3d { x: self.x * scalar, y: self.y * scalar, z: self.z * scalar }
+    }
+}
+
+impl<O, S: Copy, T: Div<S, Output = O>> Div<S> for BasicVector3d<T> {
+    type Output = BasicVector3d<O>;
+
+    fn div(self, scalar: S) -> BasicVector3d<O> {
+        BasicVector3d { x: self.x / scalar, y: self.y / scalar, z: self.z / scalar }
+    }
+}
+
+impl<T: Copy + Add<Output = T> + Sub<Output = T> + Mul<Output = T> + Div<Output = T>> BasicVector3d<T> {
+    // `t` = 0 gives `self`, `t` = 1 gives `other`
+    pub fn lerp(self, other: BasicVector3d<T>, t: T) -> BasicVector3d<T> {
+        self + (other - self) * t
+    }
+
+    // `onto` must not be a zero vector
+    pub fn project_onto(self, onto: BasicVector3d<T>) -> BasicVector3d<T> {
+        onto * (self.dot(&onto) / onto.dot(&onto))
+    }
+
+    // Mirrors the vector across the plane perpendicular to `normal`, which need not be normalised
+    pub fn reflect(self, normal: BasicVector3d<T>) -> BasicVector3d<T> {
+        let projected = self.project_onto(normal);
+        self - (projected + projected)
+    }
+}
+
 
 #[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
 pub struct Angle(f64);
@@ -399,6 +519,14 @@ impl Angle {
     }
 }
 
+impl Add for Angle {
+    type Output = Angle;
+
+    fn add(self, other: Angle) -> Angle {
+        Angle(self.0 + other.0)
+    }
+}
+
 impl Sub for Angle {
     type Output = Angle;
 
@@ -462,6 +590,33 @@ impl<
 }
 
 
+pub trait Cross<T> {
+    type Output;
+    fn cross(&self, other: &T) -> Self::Output;
+}
+
+impl<SO, MO: Sub<Output = SO>, B: Copy, A: Mul<B, Output = MO> + Copy> Cross<BasicVector<B>> for BasicVector<A> {
+    type Output = SO;
+
+    // The z component of the cross product of the vectors extended to 3D
+    fn cross(&self, other: &BasicVector<B>) -> SO {
+        self.x * other.y - self.y * other.x
+    }
+}
+
+impl<SO, MO: Sub<Output = SO>, B: Copy, A: Mul<B, Output = MO> + Copy> Cross<BasicVector3d<B>> for BasicVector3d<A> {
+    type Output = BasicVector3d<SO>;
+
+    fn cross(&self, other: &BasicVector3d<B>) -> BasicVector3d<SO> {
+        BasicVector3d {
+            x: self.y * other.z - self.z * other.y,
+            y: self.z * other.x - self.x * other.z,
+            z: self.x * other.y - self.y * other.x,
+        }
+    }
+}
+
+
 pub trait Norm {
     type Output;
     fn norm(&self) -> Self::Output;
@@ -484,6 +639,23 @@ impl Norm for BasicVector3d<f64> {
 }
 
 
+pub trait Normalize: Sized {
+    // Returns `None` for zero vectors, which have no direction
+    fn normalized(&self) -> Option<Self>;
+}
+
+impl<V: Norm<Output = f64> + Div<f64, Output = V> + Copy> Normalize for V {
+    fn normalized(&self) -> Option<V> {
+        let norm = self.norm();
+        if norm == 0.0 || !norm.is_finite() {
+            None
+        } else {
+            Some(*self / norm)
+        }
+    }
+}
+
+
 pub trait AngleWith<T> {
     fn angle_with(&self, other: &T) -> Angle
     where
@@ -521,11 +693,173 @@ impl LiesOn for Point {
 
 impl LiesOn for Point3d {
     fn lies_on(&self, a: &Point3d, b: &Point3d) -> bool {
-        (*a - *self).angle_with(&(*b - *self)).as_radians().abs() < 1e-10
+        collinear3d(*self, *a, *b)
     }
 }
 
 
+// Half of the machine epsilon, i.e. the maximal relative rounding error of a single operation
+const HALF_EPSILON: f64 = f64::EPSILON / 2.0;
+const ORIENT2D_ERROR_BOUND: f64 = (3.0 + 16.0 * HALF_EPSILON) * HALF_EPSILON;
+const ORIENT3D_ERROR_BOUND: f64 = (7.0 + 56.0 * HALF_EPSILON) * HALF_EPSILON;
+
+
+// The sign of (b - a) x (c - a): `Greater` if c lies to the left of the directed line a -> b when the y axis points up,
+// which is to the right on screen where it points down. The result is exact for all finite inputs.
+pub fn orient2d(a: BasicPoint<f64>, b: BasicPoint<f64>, c: BasicPoint<f64>) -> Ordering {
+    let left = (a.x - c.x) * (b.y - c.y);
+    let right = (a.y - c.y) * (b.x - c.x);
+    let det = left - right;
+    let error_bound = ORIENT2D_ERROR_BOUND * (left.abs() + right.abs());
+    if det > error_bound {
+        return Ordering::Greater;
+    }
+    if -det > error_bound {
+        return Ordering::Less;
+    }
+
+    let left = Expansion::difference(a.x, c.x).multiply(&Expansion::difference(b.y, c.y));
+    let right = Expansion::difference(a.y, c.y).multiply(&Expansion::difference(b.x, c.x));
+    left.add(&right.negate()).sign()
+}
+
+
+// The sign of the triple product (b - a) . ((c - a) x (d - a)): `Greater` if d lies on the side of the plane abc
+// that the normal (b - a) x (c - a) points to. The result is exact for all finite inputs.
+pub fn orient3d(a: Point3d, b: Point3d, c: Point3d, d: Point3d) -> Ordering {
+    // This evaluates det(a - d, b - d, c - d), which has the opposite sign
+    let (ad, bd, cd) = (a - d, b - d, c - d);
+    let (bc, cb) = (bd.y * cd.z, bd.z * cd.y);
+    let (ca, ac) = (cd.y * ad.z, cd.z * ad.y);
+    let (ab, ba) = (ad.y * bd.z, ad.z * bd.y);
+    let det = ad.x * (bc - cb) + bd.x * (ca - ac) + cd.x * (ab - ba);
+    let permanent = ad.x.abs() * (bc.abs() + cb.abs())
+        + bd.x.abs() * (ca.abs() + ac.abs())
+        + cd.x.abs() * (ab.abs() + ba.abs());
+    let error_bound = ORIENT3D_ERROR_BOUND * permanent;
+    if det > error_bound {
+        return Ordering::Less;
+    }
+    if -det > error_bound {
+        return Ordering::Greater;
+    }
+
+    let difference = |p: Point3d, q: Point3d| {
+        (Expansion::difference(p.x, q.x), Expansion::difference(p.y, q.y), Expansion::difference(p.z, q.z))
+    };
+    let (ad, bd, cd) = (difference(a, d), difference(b, d), difference(c, d));
+    let minor = |p: &(Expansion, Expansion, Expansion), q: &(Expansion, Expansion, Expansion)| {
+        p.1.multiply(&q.2).add(&p.2.multiply(&q.1).negate())
+    };
+    let det = ad.0.multiply(&minor(&bd, &cd))
+        .add(&bd.0.multiply(&minor(&cd, &ad)))
+        .add(&cd.0.multiply(&minor(&ad, &bd)));
+    det.sign().reverse()
+}
+
+
+// Points are collinear exactly when all three coordinate-plane projections of them are
+pub fn collinear3d(a: Point3d, b: Point3d, c: Point3d) -> bool {
+    let projections: [fn(Point3d) -> BasicPoint<f64>; 3] = [
+        |p| BasicPoint {x: p.x, y: p.y},
+        |p| BasicPoint {x: p.y, y: p.z},
+        |p| BasicPoint {x: p.z, y: p.x},
+    ];
+    projections.iter().all(|project| orient2d(project(a), project(b), project(c)) == Ordering::Equal)
+}
+
+
+// Points on the boundary count as inside; a degenerate triangle contains the points of its sides
+pub fn point_in_triangle(p: BasicPoint<f64>, a: BasicPoint<f64>, b: BasicPoint<f64>, c: BasicPoint<f64>) -> bool {
+    if orient2d(a, b, c) == Ordering::Equal {
+        return point_on_segment(p, a, b) || point_on_segment(p, b, c) || point_on_segment(p, c, a);
+    }
+    let orientations = [orient2d(a, b, p), orient2d(b, c, p), orient2d(c, a, p)];
+    !(orientations.contains(&Ordering::Less) && orientations.contains(&Ordering::Greater))
+}
+
+
+// Endpoints count as lying on the segment
+pub fn point_on_segment(p: BasicPoint<f64>, a: BasicPoint<f64>, b: BasicPoint<f64>) -> bool {
+    orient2d(a, b, p) == Ordering::Equal
+        && a.x.min(b.x) <= p.x && p.x <= a.x.max(b.x)
+        && a.y.min(b.y) <= p.y && p.y <= a.y.max(b.y)
+}
+
+
+// A number represented exactly as a sum of floats that do not overlap bitwise, ordered by increasing magnitude
+// (see Shewchuk, "Adaptive Precision Floating-Point Arithmetic and Fast Robust Geometric Predicates")
+#[derive(Debug, Clone)]
+struct Expansion(Vec<f64>);
+
+impl Expansion {
+    fn difference(a: f64, b: f64) -> Expansion {
+        Expansion(Vec::new()).grow(a).grow(-b)
+    }
+
+    // Adds a single float; zero components are dropped
+    fn grow(mut self, value: f64) -> Expansion {
+        let mut accumulator = value;
+        let mut components = Vec::with_capacity(self.0.len() + 1);
+        for component in self.0.drain(..) {
+            let (sum, error) = two_sum(accumulator, component);
+            if error != 0.0 {
+                components.push(error);
+            }
+            accumulator = sum;
+        }
+        if accumulator != 0.0 {
+            components.push(accumulator);
+        }
+        Expansion(components)
+    }
+
+    fn add(&self, other: &Expansion) -> Expansion {
+        other.0.iter().fold(self.clone(), |sum, &component| sum.grow(component))
+    }
+
+    fn multiply(&self, other: &Expansion) -> Expansion {
+        let mut result = Expansion(Vec::new());
+        for &a in &self.0 {
+            for &b in &other.0 {
+                let (product, error) = two_product(a, b);
+                result = result.grow(error).grow(product);
+            }
+        }
+        result
+    }
+
+    fn negate(&self) -> Expansion {
+        Expansion(self.0.iter().map(|component| -component).collect())
+    }
+
+    // The largest component dominates the rest
+    fn sign(&self) -> Ordering {
+        match self.0.last() {
+            Some(&largest) if largest > 0.0 => Ordering::Greater,
+            Some(&largest) if largest < 0.0 => Ordering::Less,
+            _ => Ordering::Equal,
+        }
+    }
+}
+
+
+// Returns the rounded sum and its exact rounding error
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let sum = a + b;
+    let b_virtual = sum - a;
+    let a_virtual = sum - b_virtual;
+    (sum, (a - a_virtual) + (b - b_virtual))
+}
+
+
+// Returns the rounded product and its exact rounding error, which a fused multiply-add computes without rounding
+fn two_product(a: f64, b: f64) -> (f64, f64) {
+    let product = a * b;
+    (product, a.mul_add(b, -product))
+}
+
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Par3d {
     origin: Point3d,
@@ -538,14 +872,21 @@ impl Par3d {
         Self::try_new(origin, vec1, vec2).expect("Vectors in a 3D parallelogram must not be collinear")
     }
 
+    // Checks the corners as they will be computed, so that both halves are valid triangles despite rounding
     pub fn try_new(origin: Point3d, vec1: Vector3d, vec2: Vector3d) -> Option<Par3d> {
-        let angle = vec1.angle_with(&vec2);
-        if angle.as_radians().abs() < 1e-12 || (angle - Angle::half_circle()).as_radians().abs() < 1e-12 { 
-            // Vectors are collinear
-            None
-        } else {
-            Some(Par3d {origin, vec1, vec2})
-        }
+        let (b, c) = (origin + vec1, origin + vec2);
+        let opposite = origin + vec1 + vec2;
+        Triangle3d::try_new(origin, b, c)?;
+        Triangle3d::try_new(opposite, c, b)?;
+        Some(Par3d {origin, vec1, vec2})
+    }
+
+    pub fn origin(&self) -> Point3d {
+        self.origin
+    }
+
+    pub fn vectors(&self) -> (Vector3d, Vector3d) {
+        (self.vec1, self.vec2)
     }
 
     pub fn to_triangles(&self) -> (Triangle3d, Triangle3d) {
@@ -560,3 +901,576 @@ impl Par3d {
         (tri1, tri2)
     }
 }
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cuboid {
+    origin: Point3d,
+    edges: (Vector3d, Vector3d, Vector3d),
+}
+
+impl Cuboid {
+    pub fn new(origin: Point3d, edge1: Vector3d, edge2: Vector3d, edge3: Vector3d) -> Cuboid {
+        Self::try_new(origin, edge1, edge2, edge3).expect("Edges of a cuboid must not be coplanar")
+    }
+
+    pub fn try_new(origin: Point3d, edge1: Vector3d, edge2: Vector3d, edge3: Vector3d) -> Option<Cuboid> {
+        let corners = [origin, origin + edge1, origin + edge2, origin + edge3];
+        if corners.iter().any(|p| !(p.x.is_finite() && p.y.is_finite() && p.z.is_finite())) {
+            return None;
+        }
+        if orient3d(corners[0], corners[1], corners[2], corners[3]) == Ordering::Equal {
+            // Edges are coplanar
+            return None;
+        }
+        let cuboid = Cuboid {origin, edges: (edge1, edge2, edge3)};
+        // Faces are checked as well, since rounding of their corners could still flatten them
+        for &(face_origin, vec1, vec2) in cuboid.face_parameters().iter() {
+            Par3d::try_new(face_origin, vec1, vec2)?;
+        }
+        Some(cuboid)
+    }
+
+    // An axis-aligned cuboid spanning two opposite corners
+    pub fn from_bounds(min: Point3d, max: Point3d) -> Cuboid {
+        let size = max - min;
+        Cuboid::new(
+            min,
+            Vector3d {x: size.x, y: 0.0, z: 0.0},
+            Vector3d {x: 0.0, y: size.y, z: 0.0},
+            Vector3d {x: 0.0, y: 0.0, z: size.z},
+        )
+    }
+
+    pub fn origin(&self) -> Point3d {
+        self.origin
+    }
+
+    pub fn edges(&self) -> (Vector3d, Vector3d, Vector3d) {
+        self.edges
+    }
+
+    pub fn vertices(&self) -> [Point3d; 8] {
+        let (u, v, w) = self.edges;
+        let o = self.origin;
+        [o, o + u, o + v, o + u + v, o + w, o + u + w, o + v + w, o + u + v + w]
+    }
+
+    // Faces are ordered as: -u, +u, -v, +v, -w, +w
+    pub fn faces(&self) -> [Par3d; 6] {
+        let parameters = self.face_parameters();
+        let face = |index: usize| {
+            let (origin, vec1, vec2) = parameters[index];
+            Par3d::new(origin, vec1, vec2)
+        };
+        [face(0), face(1), face(2), face(3), face(4), face(5)]
+    }
+
+    fn face_parameters(&self) -> [(Point3d, Vector3d, Vector3d); 6] {
+        let (u, v, w) = self.edges;
+        let o = self.origin;
+        [(o, v, w), (o + u, v, w), (o, u, w), (o + v, u, w), (o, u, v), (o + w, u, v)]
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Segment3d {
+    pub start: Point3d,
+    pub end: Point3d,
+}
+
+impl Segment3d {
+    pub fn new(start: Point3d, end: Point3d) -> Segment3d {
+        Segment3d {start, end}
+    }
+
+    pub fn direction(&self) -> Vector3d {
+        self.end - self.start
+    }
+
+    pub fn length(&self) -> f64 {
+        self.direction().norm()
+    }
+
+    // `t` = 0 gives the start, `t` = 1 gives the end
+    pub fn point_at(&self, t: f64) -> Point3d {
+        self.start + self.direction() * t
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ray3d {
+    origin: Point3d,
+    direction: Vector3d,
+}
+
+impl Ray3d {
+    pub fn new(origin: Point3d, direction: Vector3d) -> Ray3d {
+        Ray3d::try_new(origin, direction).expect("A ray needs a finite non-zero direction")
+    }
+
+    // The direction gets normalised, so that distances along the ray are Euclidean
+    pub fn try_new(origin: Point3d, direction: Vector3d) -> Option<Ray3d> {
+        Some(Ray3d {origin, direction: direction.normalized()?})
+    }
+
+    pub fn origin(&self) -> Point3d {
+        self.origin
+    }
+
+    pub fn direction(&self) -> Vector3d {
+        self.direction
+    }
+
+    pub fn point_at(&self, distance: f64) -> Point3d {
+        self.origin + self.direction * distance
+    }
+}
+
+
+// The points p with normal . p = offset
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Plane {
+    normal: Vector3d,
+    offset: f64,
+}
+
+impl Plane {
+    pub fn new(normal: Vector3d, offset: f64) -> Plane {
+        Plane::try_new(normal, offset).expect("A plane needs a finite non-zero normal")
+    }
+
+    pub fn try_new(normal: Vector3d, offset: f64) -> Option<Plane> {
+        let norm = normal.norm();
+        Some(Plane {normal: normal.normalized()?, offset: offset / norm})
+    }
+
+    pub fn from_point_normal(point: Point3d, normal: Vector3d) -> Option<Plane> {
+        let normal = normal.normalized()?;
+        Some(Plane {normal, offset: normal.dot(&point.as_vector())})
+    }
+
+    // The normal follows the winding a -> b -> c by the right-hand rule; `None` if it underflows to zero
+    pub fn from_triangle(tri: &Triangle3d) -> Option<Plane> {
+        Plane::from_point_normal(tri.a, (tri.b - tri.a).cross(&(tri.c - tri.a)))
+    }
+
+    pub fn normal(&self) -> Vector3d {
+        self.normal
+    }
+
+    pub fn offset(&self) -> f64 {
+        self.offset
+    }
+
+    pub fn signed_distance(&self, point: Point3d) -> f64 {
+        self.normal.dot(&point.as_vector()) - self.offset
+    }
+
+    // `Greater` on the side the normal points to; points closer than the rounding error count as lying on the plane
+    pub fn side_of(&self, point: Point3d) -> Ordering {
+        let distance = self.signed_distance(point);
+        let error_bound = 8.0 * f64::EPSILON * (point.as_vector().norm() + self.offset.abs());
+        if distance > error_bound {
+            Ordering::Greater
+        } else if distance < -error_bound {
+            Ordering::Less
+        } else {
+            Ordering::Equal
+        }
+    }
+
+    pub fn project_point(&self, point: Point3d) -> Point3d {
+        point - self.normal * self.signed_distance(point)
+    }
+}
+
+
+// An axis-aligned bounding box; `min` is less than or equal to `max` in every coordinate
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    min: Point3d,
+    max: Point3d,
+}
+
+impl Aabb {
+    // The corners may be given in any order
+    pub fn new(a: Point3d, b: Point3d) -> Aabb {
+        Aabb {
+            min: Point3d {x: a.x.min(b.x), y: a.y.min(b.y), z: a.z.min(b.z)},
+            max: Point3d {x: a.x.max(b.x), y: a.y.max(b.y), z: a.z.max(b.z)},
+        }
+    }
+
+    pub fn from_points(points: impl IntoIterator<Item = Point3d>) -> Option<Aabb> {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+        Some(points.fold(Aabb::new(first, first), Aabb::expanded_to))
+    }
+
+    pub fn min(&self) -> Point3d {
+        self.min
+    }
+
+    pub fn max(&self) -> Point3d {
+        self.max
+    }
+
+    pub fn size(&self) -> Vector3d {
+        self.max - self.min
+    }
+
+    pub fn center(&self) -> Point3d {
+        self.min + self.size() * 0.5
+    }
+
+    pub fn contains(&self, point: Point3d) -> bool {
+        self.min.x <= point.x && point.x <= self.max.x
+            && self.min.y <= point.y && point.y <= self.max.y
+            && self.min.z <= point.z && point.z <= self.max.z
+    }
+
+    pub fn expanded_to(self, point: Point3d) -> Aabb {
+        self.union(&Aabb::new(point, point))
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        let (a, b) = (self, other);
+        Aabb {
+            min: Point3d {x: a.min.x.min(b.min.x), y: a.min.y.min(b.min.y), z: a.min.z.min(b.min.z)},
+            max: Point3d {x: a.max.x.max(b.max.x), y: a.max.y.max(b.max.y), z: a.max.z.max(b.max.z)},
+        }
+    }
+
+    // Flat boxes have no cuboid counterpart
+    pub fn to_cuboid(self) -> Option<Cuboid> {
+        let size = self.size();
+        Cuboid::try_new(
+            self.min,
+            Vector3d {x: size.x, y: 0.0, z: 0.0},
+            Vector3d {x: 0.0, y: size.y, z: 0.0},
+            Vector3d {x: 0.0, y: 0.0, z: size.z},
+        )
+    }
+}
+
+
+// Where a ray hits a triangle: the distance along the ray and the coordinates (u, v) of the hit point
+// in the basis of the triangle's sides, so that point = a + u * (b - a) + v * (c - a)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TriangleHit {
+    pub distance: f64,
+    pub u: f64,
+    pub v: f64,
+}
+
+
+// Both sides of the triangle are hit; rays lying in the triangle's plane are not (Möller–Trumbore)
+pub fn intersect_ray_triangle(ray: &Ray3d, tri: &Triangle3d) -> Option<TriangleHit> {
+    let edge1 = tri.b - tri.a;
+    let edge2 = tri.c - tri.a;
+    let p = ray.direction.cross(&edge2);
+    let det = edge1.dot(&p);
+    if det.abs() <= f64::EPSILON * edge1.norm() * edge2.norm() {
+        return None;
+    }
+
+    let to_origin = ray.origin - tri.a;
+    let u = to_origin.dot(&p) / det;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = to_origin.cross(&edge1);
+    let v = ray.direction.dot(&q) / det;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let distance = edge2.dot(&q) / det;
+    if distance < 0.0 {
+        None
+    } else {
+        Some(TriangleHit {distance, u, v})
+    }
+}
+
+
+// The distance along the ray to the plane; rays parallel to the plane miss it, even if they lie in it
+pub fn intersect_ray_plane(ray: &Ray3d, plane: &Plane) -> Option<f64> {
+    let approach = plane.normal.dot(&ray.direction);
+    if approach == 0.0 {
+        return None;
+    }
+    let distance = -plane.signed_distance(ray.origin) / approach;
+    if distance >= 0.0 && distance.is_finite() {
+        Some(distance)
+    } else {
+        None
+    }
+}
+
+
+// Distances along the ray at which it enters and leaves the box; the entry is 0 for rays starting inside
+pub fn intersect_ray_aabb(ray: &Ray3d, aabb: &Aabb) -> Option<(f64, f64)> {
+    let origin = [ray.origin.x, ray.origin.y, ray.origin.z];
+    let direction = [ray.direction.x, ray.direction.y, ray.direction.z];
+    let min = [aabb.min.x, aabb.min.y, aabb.min.z];
+    let max = [aabb.max.x, aabb.max.y, aabb.max.z];
+
+    let (mut entry, mut exit) = (0.0, f64::INFINITY);
+    for axis in 0..3 {
+        if direction[axis] == 0.0 {
+            // Parallel to the slab: either always inside it or never
+            if origin[axis] < min[axis] || origin[axis] > max[axis] {
+                return None;
+            }
+            continue;
+        }
+        let t1 = (min[axis] - origin[axis]) / direction[axis];
+        let t2 = (max[axis] - origin[axis]) / direction[axis];
+        entry = t1.min(t2).max(entry);
+        exit = t1.max(t2).min(exit);
+        if entry > exit {
+            return None;
+        }
+    }
+    Some((entry, exit))
+}
+
+
+// The overlap of two boxes; boxes that only touch overlap in a flat box
+pub fn intersect_aabbs(a: &Aabb, b: &Aabb) -> Option<Aabb> {
+    let min = Point3d {x: a.min.x.max(b.min.x), y: a.min.y.max(b.min.y), z: a.min.z.max(b.min.z)};
+    let max = Point3d {x: a.max.x.min(b.max.x), y: a.max.y.min(b.max.y), z: a.max.z.min(b.max.z)};
+    if min.x <= max.x && min.y <= max.y && min.z <= max.z {
+        Some(Aabb {min, max})
+    } else {
+        None
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrianglePlaneIntersection {
+    // The triangle touches the plane with a single vertex
+    Point(Point3d),
+    Segment(Segment3d),
+    Coplanar,
+}
+
+
+pub fn intersect_triangle_plane(tri: &Triangle3d, plane: &Plane) -> Option<TrianglePlaneIntersection> {
+    let vertices = [tri.a, tri.b, tri.c];
+    let sides = [plane.side_of(tri.a), plane.side_of(tri.b), plane.side_of(tri.c)];
+    if sides.iter().all(|&side| side == Ordering::Equal) {
+        return Some(TrianglePlaneIntersection::Coplanar);
+    }
+    if !sides.contains(&Ordering::Equal) && (!sides.contains(&Ordering::Less) || !sides.contains(&Ordering::Greater)) {
+        return None;
+    }
+
+    // Vertices on the plane, and points where edges cross it
+    let mut points = Vec::with_capacity(2);
+    for index in 0..3 {
+        let next = (index + 1) % 3;
+        if sides[index] == Ordering::Equal {
+            points.push(vertices[index]);
+        } else if sides[next] != Ordering::Equal && sides[index] != sides[next] {
+            let (from, to) = (vertices[index], vertices[next]);
+            let (distance_from, distance_to) = (plane.signed_distance(from), plane.signed_distance(to));
+            let t = distance_from / (distance_from - distance_to);
+            points.push(from + (to - from) * t);
+        }
+    }
+    match points[..] {
+        [point] => Some(TrianglePlaneIntersection::Point(point)),
+        [start, end] => Some(TrianglePlaneIntersection::Segment(Segment3d {start, end})),
+        _ => None,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Coordinates of nearly degenerate points are multiples of 1 / SCALE, so that scaled up they become exact integers
+    const SCALE: f64 = (1u64 << 53) as f64;
+
+    fn exact(value: f64) -> i128 {
+        let scaled = value * SCALE;
+        assert_eq!(scaled.fract(), 0.0, "{} is not a multiple of the test grid", value);
+        scaled as i128
+    }
+
+    fn point2d(x: f64, y: f64) -> BasicPoint<f64> {
+        BasicPoint {x, y}
+    }
+
+    fn point3d(x: f64, y: f64, z: f64) -> Point3d {
+        Point3d {x, y, z}
+    }
+
+    fn vector3d(x: f64, y: f64, z: f64) -> Vector3d {
+        Vector3d {x, y, z}
+    }
+
+    fn naive_orient2d(a: BasicPoint<f64>, b: BasicPoint<f64>, c: BasicPoint<f64>) -> Ordering {
+        let det = (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x);
+        det.partial_cmp(&0.0).unwrap()
+    }
+
+    fn exact_orient2d(a: BasicPoint<f64>, b: BasicPoint<f64>, c: BasicPoint<f64>) -> Ordering {
+        let (ax, ay, bx, by, cx, cy) = (exact(a.x), exact(a.y), exact(b.x), exact(b.y), exact(c.x), exact(c.y));
+        ((bx - ax) * (cy - ay) - (by - ay) * (cx - ax)).cmp(&0)
+    }
+
+    fn naive_orient3d(a: Point3d, b: Point3d, c: Point3d, d: Point3d) -> Ordering {
+        let det = (b - a).dot(&(c - a).cross(&(d - a)));
+        det.partial_cmp(&0.0).unwrap()
+    }
+
+    #[test]
+    fn orient2d_is_exact_for_nearly_collinear_points() {
+        // Points a grid step apart around the line y = x, far below the rounding error of the naive determinant
+        let step = 1.0 / SCALE;
+        let (b, c) = (point2d(12.0, 12.0), point2d(24.0, 24.0));
+        let mut naive_mistakes = 0;
+        for i in 0..32 {
+            for j in 0..32 {
+                let a = point2d(0.5 + i as f64 * step, 0.5 + j as f64 * step);
+                assert_eq!(orient2d(a, b, c), exact_orient2d(a, b, c), "{:?}", a);
+                if naive_orient2d(a, b, c) != exact_orient2d(a, b, c) {
+                    naive_mistakes += 1;
+                }
+            }
+        }
+        assert!(naive_mistakes > 0);
+    }
+
+    #[test]
+    fn orient3d_is_exact_for_nearly_coplanar_points() {
+        // With d straight above a, the triple product is the 2D determinant of a, b and c
+        let step = 1.0 / SCALE;
+        let (b, c) = (point3d(12.0, 12.0, 0.0), point3d(24.0, 24.0, 0.0));
+        let flat = |p: Point3d| point2d(p.x, p.y);
+        let mut naive_mistakes = 0;
+        for i in 0..32 {
+            for j in 0..32 {
+                let a = point3d(0.5 + i as f64 * step, 0.5 + j as f64 * step, 0.0);
+                let d = point3d(a.x, a.y, 1.0);
+                let expected = exact_orient2d(flat(a), flat(b), flat(c));
+                assert_eq!(orient3d(a, b, c, d), expected, "{:?}", a);
+                if naive_orient3d(a, b, c, d) != expected {
+                    naive_mistakes += 1;
+                }
+            }
+        }
+        assert!(naive_mistakes > 0);
+    }
+
+    #[test]
+    fn orientation_signs_follow_the_documented_conventions() {
+        let (a, b) = (point2d(0.0, 0.0), point2d(1.0, 0.0));
+        assert_eq!(orient2d(a, b, point2d(0.0, 1.0)), Ordering::Greater);
+        assert_eq!(orient2d(a, b, point2d(0.0, -1.0)), Ordering::Less);
+        assert_eq!(orient2d(a, b, point2d(2.0, 0.0)), Ordering::Equal);
+
+        let (a, b, c) = (point3d(0.0, 0.0, 0.0), point3d(1.0, 0.0, 0.0), point3d(0.0, 1.0, 0.0));
+        assert_eq!(orient3d(a, b, c, point3d(0.0, 0.0, 1.0)), Ordering::Greater);
+        assert_eq!(orient3d(a, b, c, point3d(0.0, 0.0, -1.0)), Ordering::Less);
+        assert_eq!(orient3d(a, b, c, point3d(5.0, 7.0, 0.0)), Ordering::Equal);
+    }
+
+    #[test]
+    fn segment_points_are_interpolated() {
+        let segment = Segment3d::new(point3d(1.0, 2.0, 3.0), point3d(4.0, 6.0, 3.0));
+        assert_eq!(segment.length(), 5.0);
+        assert_eq!(segment.point_at(0.0), segment.start);
+        assert_eq!(segment.point_at(1.0), segment.end);
+        assert_eq!(segment.point_at(0.5), point3d(2.5, 4.0, 3.0));
+    }
+
+    #[test]
+    fn ray_hits_plane_in_front_of_it() {
+        let plane = Plane::new(vector3d(0.0, 0.0, 2.0), 10.0);
+        assert_eq!(plane.offset(), 5.0);
+        let ray = Ray3d::new(point3d(1.0, 1.0, 0.0), vector3d(0.0, 0.0, 3.0));
+        assert_eq!(intersect_ray_plane(&ray, &plane), Some(5.0));
+        assert_eq!(ray.point_at(5.0), point3d(1.0, 1.0, 5.0));
+    }
+
+    #[test]
+    fn ray_misses_plane_behind_it_or_parallel_to_it() {
+        let plane = Plane::new(vector3d(0.0, 0.0, 1.0), 5.0);
+        let away = Ray3d::new(point3d(0.0, 0.0, 0.0), vector3d(0.0, 0.0, -1.0));
+        assert_eq!(intersect_ray_plane(&away, &plane), None);
+        let parallel = Ray3d::new(point3d(0.0, 0.0, 0.0), vector3d(1.0, 0.0, 0.0));
+        assert_eq!(intersect_ray_plane(&parallel, &plane), None);
+        let inside = Ray3d::new(point3d(0.0, 0.0, 5.0), vector3d(1.0, 0.0, 0.0));
+        assert_eq!(intersect_ray_plane(&inside, &plane), None);
+    }
+
+    #[test]
+    fn ray_enters_and_leaves_box() {
+        let aabb = Aabb::new(point3d(1.0, 1.0, 1.0), point3d(-1.0, -1.0, -1.0));
+        let ray = Ray3d::new(point3d(-3.0, 0.0, 0.0), vector3d(1.0, 0.0, 0.0));
+        assert_eq!(intersect_ray_aabb(&ray, &aabb), Some((2.0, 4.0)));
+        let from_inside = Ray3d::new(point3d(0.0, 0.0, 0.0), vector3d(0.0, 1.0, 0.0));
+        assert_eq!(intersect_ray_aabb(&from_inside, &aabb), Some((0.0, 1.0)));
+    }
+
+    #[test]
+    fn ray_misses_box_beside_or_behind_it() {
+        let aabb = Aabb::new(point3d(-1.0, -1.0, -1.0), point3d(1.0, 1.0, 1.0));
+        let beside = Ray3d::new(point3d(-3.0, 2.0, 0.0), vector3d(1.0, 0.0, 0.0));
+        assert_eq!(intersect_ray_aabb(&beside, &aabb), None);
+        let diagonal = Ray3d::new(point3d(-3.0, 0.0, 0.0), vector3d(1.0, 2.0, 0.0));
+        assert_eq!(intersect_ray_aabb(&diagonal, &aabb), None);
+        let behind = Ray3d::new(point3d(3.0, 0.0, 0.0), vector3d(1.0, 0.0, 0.0));
+        assert_eq!(intersect_ray_aabb(&behind, &aabb), None);
+    }
+
+    #[test]
+    fn overlapping_boxes_intersect_in_a_box() {
+        let a = Aabb::new(point3d(0.0, 0.0, 0.0), point3d(2.0, 2.0, 2.0));
+        let b = Aabb::new(point3d(1.0, 1.0, 1.0), point3d(3.0, 3.0, 3.0));
+        assert_eq!(intersect_aabbs(&a, &b), Some(Aabb::new(point3d(1.0, 1.0, 1.0), point3d(2.0, 2.0, 2.0))));
+        let touching = Aabb::new(point3d(2.0, 0.0, 0.0), point3d(3.0, 2.0, 2.0));
+        assert_eq!(intersect_aabbs(&a, &touching).map(|overlap| overlap.size().x), Some(0.0));
+        let apart = Aabb::new(point3d(0.0, 3.0, 0.0), point3d(2.0, 4.0, 2.0));
+        assert_eq!(intersect_aabbs(&a, &apart), None);
+    }
+
+    #[test]
+    fn triangle_crossing_plane_is_cut_along_a_segment() {
+        let plane = Plane::new(vector3d(0.0, 0.0, 1.0), 0.0);
+        let tri = Triangle3d::new(point3d(0.0, 0.0, -1.0), point3d(2.0, 0.0, 1.0), point3d(0.0, 2.0, 1.0));
+        let expected = Segment3d::new(point3d(1.0, 0.0, 0.0), point3d(0.0, 1.0, 0.0));
+        match intersect_triangle_plane(&tri, &plane) {
+            Some(TrianglePlaneIntersection::Segment(segment)) => {
+                let matches = |s: Segment3d| s.start == expected.start && s.end == expected.end;
+                assert!(matches(segment) || matches(Segment3d::new(segment.end, segment.start)), "{:?}", segment);
+            },
+            other => panic!("Expected a segment, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn triangle_touching_plane_meets_it_in_a_point() {
+        let plane = Plane::new(vector3d(0.0, 0.0, 1.0), 0.0);
+        let tri = Triangle3d::new(point3d(0.0, 0.0, 0.0), point3d(2.0, 0.0, 1.0), point3d(0.0, 2.0, 1.0));
+        let expected = TrianglePlaneIntersection::Point(point3d(0.0, 0.0, 0.0));
+        assert_eq!(intersect_triangle_plane(&tri, &plane), Some(expected));
+    }
+
+    #[test]
+    fn triangle_beside_or_in_plane() {
+        let plane = Plane::new(vector3d(0.0, 0.0, 1.0), 0.0);
+        let above = Triangle3d::new(point3d(0.0, 0.0, 1.0), point3d(2.0, 0.0, 1.0), point3d(0.0, 2.0, 3.0));
+        assert_eq!(intersect_triangle_plane(&above, &plane), None);
+        let coplanar = Triangle3d::new(point3d(0.0, 0.0, 0.0), point3d(2.0, 0.0, 0.0), point3d(0.0, 2.0, 0.0));
+        assert_eq!(intersect_triangle_plane(&coplanar, &plane), Some(TrianglePlaneIntersection::Coplanar));
+    }
+}
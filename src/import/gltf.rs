@@ -0,0 +1,858 @@
+use super::json::{parse_json, JsonValue};
+use super::{ImportError, color_from_unit_floats};
+use crate::geometry::{Angle, BasicPoint, Normalize, Point3d, Vector3d};
+use crate::linalg::{Matrix4d, Quaternion, Transform};
+use crate::mesh::{Mesh, Vertex};
+use crate::render::{RGB, Camera};
+use crate::texture::Texture;
+
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+
+
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+const GLB_JSON_CHUNK: u32 = 0x4e4f_534a;
+const GLB_BIN_CHUNK: u32 = 0x004e_4942;
+
+const MODE_TRIANGLES: usize = 4;
+const MODE_TRIANGLE_STRIP: usize = 5;
+const MODE_TRIANGLE_FAN: usize = 6;
+
+// Accessors without a buffer view take no space in the file, so their count is not bounded by the data
+const MAX_UNBACKED_ACCESSOR_COUNT: usize = 1 << 24;
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GltfPrimitive {
+    pub mesh: Mesh,
+    pub material: Option<usize>,
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GltfMesh {
+    pub name: String,
+    pub primitives: Vec<GltfPrimitive>,
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GltfMaterial {
+    pub name: String,
+    pub base_color: RGB,
+    pub base_color_texture: Option<usize>,
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GltfCamera {
+    Perspective {
+        name: String,
+        yfov: Angle,
+        aspect_ratio: Option<f64>,
+    },
+    Orthographic {
+        name: String,
+    },
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GltfNode {
+    pub name: String,
+    pub transform: Transform,
+    pub children: Vec<usize>,
+    pub mesh: Option<usize>,
+    pub camera: Option<usize>,
+}
+
+
+// A mesh primitive placed into the world, with coordinates converted to the renderer's convention
+#[derive(Debug, Clone, PartialEq)]
+pub struct GltfInstance {
+    pub node: usize,
+    pub mesh: Mesh,
+    pub material: Option<usize>,
+}
+
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GltfScene {
+    pub nodes: Vec<GltfNode>,
+    pub roots: Vec<usize>,
+    pub meshes: Vec<GltfMesh>,
+    pub materials: Vec<GltfMaterial>,
+    // Shared with the shaders drawing the meshes
+    pub textures: Vec<Rc<Texture>>,
+    pub cameras: Vec<GltfCamera>,
+}
+
+impl GltfScene {
+    pub fn world_transforms(&self) -> Vec<Transform> {
+        let mut result = vec![Transform::identity(); self.nodes.len()];
+        let mut stack: Vec<(usize, Transform)> = self.roots.iter().map(|&root| (root, Transform::identity())).collect();
+        while let Some((index, parent)) = stack.pop() {
+            let node = &self.nodes[index];
+            let world = parent * node.transform;
+            result[index] = world;
+            stack.extend(node.children.iter().map(|&child| (child, world)));
+        }
+        result
+    }
+
+    pub fn instances(&self) -> Vec<GltfInstance> {
+        let world_transforms = self.world_transforms();
+        let mut result = Vec::new();
+        for (index, node) in self.nodes.iter().enumerate() {
+            let mesh = match node.mesh {
+                Some(mesh) => &self.meshes[mesh],
+                None => continue,
+            };
+            let transform = &world_transforms[index];
+            let normal_matrix = transform.normal_matrix();
+            for primitive in &mesh.primitives {
+                let mut world_mesh = primitive.mesh.clone();
+                for vertex in world_mesh.vertices_mut() {
+                    vertex.position = to_renderer_point(transform.apply_point(vertex.position));
+                    // Normals of a mesh squashed flat by a zero scale are meaningless and get dropped
+                    vertex.normal = vertex
+                        .normal
+                        .and_then(|normal| (normal_matrix? * normal).normalized())
+                        .map(to_renderer_vector);
+                }
+                result.push(GltfInstance {node: index, mesh: world_mesh, material: primitive.material});
+            }
+        }
+        result
+    }
+
+    pub fn base_color_texture(&self, material: usize) -> Option<&Rc<Texture>> {
+        self.materials[material].base_color_texture.map(|texture| &self.textures[texture])
+    }
+
+    // Cameras attached to nodes, in node order; the renderer has no roll, so it is dropped
+    pub fn renderer_cameras(&self, default_aspect_ratio: f64) -> Vec<Camera> {
+        let world_transforms = self.world_transforms();
+        let mut result = Vec::new();
+        for (index, node) in self.nodes.iter().enumerate() {
+            let (yfov, aspect_ratio) = match node.camera.map(|camera| &self.cameras[camera]) {
+                Some(GltfCamera::Perspective {yfov, aspect_ratio, ..}) => (*yfov, *aspect_ratio),
+                // Orthographic projection is not supported by the renderer, such cameras get a default field of view
+                Some(GltfCamera::Orthographic {..}) => (Angle::from_degrees(70.0), None),
+                None => continue,
+            };
+
+            let transform = &world_transforms[index];
+            let position = to_renderer_point(transform.apply_point(Point3d {x: 0.0, y: 0.0, z: 0.0}));
+            let forward = to_renderer_vector(transform.apply_vector(Vector3d {x: 0.0, y: 0.0, z: -1.0}));
+            let azimuth = Angle::from_radians(forward.z.atan2(forward.x));
+            let vertical_angle = Angle::from_radians(forward.y.atan2(forward.x.hypot(forward.z)));
+            let aspect_ratio = aspect_ratio.unwrap_or(default_aspect_ratio);
+            let hfov = Angle::from_radians(2.0 * (aspect_ratio * (yfov / 2.0).as_radians().tan()).atan());
+            result.push(Camera::with_parameters(position, azimuth, vertical_angle, hfov, yfov));
+        }
+        result
+    }
+}
+
+
+pub fn load_gltf(path: impl AsRef<Path>) -> Result<GltfScene, ImportError> {
+    let path = path.as_ref();
+    let data = fs::read(path)?;
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+    let result = if data.starts_with(GLB_MAGIC) {
+        parse_glb(&data, Some(directory))
+    } else {
+        let source = std::str::from_utf8(&data).map_err(|_| ImportError::invalid("glTF JSON is not valid UTF-8"))?;
+        parse_gltf(source, None, Some(directory))
+    };
+    result.map_err(|e| e.in_file(path))
+}
+
+
+pub fn parse_glb(data: &[u8], directory: Option<&Path>) -> Result<GltfScene, ImportError> {
+    let word = |offset: usize| -> Result<u32, ImportError> {
+        data
+            .get(offset..offset + 4)
+            .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .ok_or_else(|| ImportError::invalid("Truncated GLB file"))
+    };
+
+    if !data.starts_with(GLB_MAGIC) {
+        return Err(ImportError::invalid("Missing GLB magic number"));
+    }
+    if word(4)? != 2 {
+        return Err(ImportError::invalid(format!("Unsupported GLB version {}", word(4)?)));
+    }
+    let length = (word(8)? as usize).min(data.len());
+
+    let mut json = None;
+    let mut bin = None;
+    let mut offset = 12;
+    while offset + 8 <= length {
+        let chunk_length = word(offset)? as usize;
+        let chunk_type = word(offset + 4)?;
+        let chunk = data
+            .get(offset + 8..offset + 8 + chunk_length)
+            .ok_or_else(|| ImportError::invalid("Truncated GLB chunk"))?;
+        match chunk_type {
+            GLB_JSON_CHUNK if json.is_none() => json = Some(chunk),
+            GLB_BIN_CHUNK if bin.is_none() => bin = Some(chunk),
+            _ => {},
+        }
+        // Chunks are padded to 4 bytes
+        offset += 8 + chunk_length.div_ceil(4) * 4;
+    }
+
+    let json = json.ok_or_else(|| ImportError::invalid("GLB file has no JSON chunk"))?;
+    let source = std::str::from_utf8(json).map_err(|_| ImportError::invalid("glTF JSON is not valid UTF-8"))?;
+    parse_gltf(source, bin, directory)
+}
+
+
+// `bin` is the binary chunk of a GLB file, `directory` is used to resolve external files
+pub fn parse_gltf(source: &str, bin: Option<&[u8]>, directory: Option<&Path>) -> Result<GltfScene, ImportError> {
+    let document = parse_json(source)?;
+    let version = document.get("asset").and_then(|asset| asset.get("version")).and_then(JsonValue::as_str);
+    if !version.is_some_and(|version| version.starts_with("2.")) {
+        return Err(ImportError::invalid(format!("Unsupported glTF version {:?}", version)));
+    }
+
+    let loader = Loader {document: &document, directory};
+    let buffers = loader
+        .array("buffers")
+        .iter()
+        .enumerate()
+        .map(|(index, buffer)| loader.buffer(index, buffer, bin))
+        .collect::<Result<Vec<_>, _>>()?;
+    let loader = BufferLoader {loader, buffers};
+
+    let textures = loader.textures()?;
+    let materials = loader
+        .loader
+        .array("materials")
+        .iter()
+        .map(|material| loader.material(material, textures.len()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let meshes = loader
+        .loader
+        .array("meshes")
+        .iter()
+        .map(|mesh| loader.mesh(mesh, &materials))
+        .collect::<Result<Vec<_>, _>>()?;
+    let cameras = loader.loader.array("cameras").iter().map(camera).collect::<Result<Vec<_>, _>>()?;
+    let nodes = loader
+        .loader
+        .array("nodes")
+        .iter()
+        .map(|node| loader.node(node, meshes.len(), cameras.len()))
+        .collect::<Result<Vec<_>, _>>()?;
+    validate_hierarchy(&nodes)?;
+
+    let roots = loader.roots(&nodes)?;
+    Ok(GltfScene {nodes, roots, meshes, materials, textures, cameras})
+}
+
+
+struct Loader<'a> {
+    document: &'a JsonValue,
+    directory: Option<&'a Path>,
+}
+
+impl<'a> Loader<'a> {
+    fn array(&self, key: &str) -> &'a [JsonValue] {
+        self.document.get(key).and_then(JsonValue::as_array).unwrap_or(&[])
+    }
+
+    fn external_file(&self, uri: &str) -> Result<Vec<u8>, ImportError> {
+        if let Some(data) = uri.strip_prefix("data:") {
+            let (header, payload) = data
+                .split_once(',')
+                .ok_or_else(|| ImportError::invalid("Malformed data URI"))?;
+            if !header.ends_with(";base64") {
+                return Err(ImportError::invalid("Only base64 data URIs are supported"));
+            }
+            return decode_base64(payload);
+        }
+
+        let directory = self
+            .directory
+            .ok_or_else(|| ImportError::invalid(format!("Cannot resolve external file `{}`", uri)))?;
+        Ok(fs::read(directory.join(percent_decode(uri)))?)
+    }
+
+    fn buffer(&self, index: usize, buffer: &JsonValue, bin: Option<&[u8]>) -> Result<Vec<u8>, ImportError> {
+        let length = required_usize(buffer, "byteLength", "buffer")?;
+        let data = match buffer.get("uri").and_then(JsonValue::as_str) {
+            Some(uri) => self.external_file(uri)?,
+            None if index == 0 => bin
+                .ok_or_else(|| ImportError::invalid("Buffer 0 has no URI and there is no GLB chunk"))?
+                .to_vec(),
+            None => return Err(ImportError::invalid(format!("Buffer {} has no URI", index))),
+        };
+        if data.len() < length {
+            return Err(ImportError::invalid(format!("Buffer {} is shorter than its `byteLength`", index)));
+        }
+        Ok(data)
+    }
+}
+
+
+struct BufferLoader<'a> {
+    loader: Loader<'a>,
+    buffers: Vec<Vec<u8>>,
+}
+
+impl BufferLoader<'_> {
+    fn buffer_view(&self, index: usize) -> Result<(&[u8], Option<usize>), ImportError> {
+        let view = self
+            .loader
+            .array("bufferViews")
+            .get(index)
+            .ok_or_else(|| ImportError::invalid(format!("Buffer view {} does not exist", index)))?;
+        let buffer = required_usize(view, "buffer", "buffer view")?;
+        let offset = optional_usize(view, "byteOffset")?.unwrap_or(0);
+        let length = required_usize(view, "byteLength", "buffer view")?;
+        let stride = optional_usize(view, "byteStride")?;
+        let data = self
+            .buffers
+            .get(buffer)
+            .zip(offset.checked_add(length))
+            .and_then(|(data, end)| data.get(offset..end))
+            .ok_or_else(|| ImportError::invalid(format!("Buffer view {} is out of range", index)))?;
+        Ok((data, stride))
+    }
+
+    // Reads an accessor as rows of components, applying normalization of integer components
+    fn accessor(&self, index: usize) -> Result<Vec<Vec<f64>>, ImportError> {
+        let accessor = self
+            .loader
+            .array("accessors")
+            .get(index)
+            .ok_or_else(|| ImportError::invalid(format!("Accessor {} does not exist", index)))?;
+        if accessor.get("sparse").is_some() {
+            return Err(ImportError::invalid("Sparse accessors are not supported"));
+        }
+
+        let count = required_usize(accessor, "count", "accessor")?;
+        let component_type = required_usize(accessor, "componentType", "accessor")?;
+        let normalized = matches!(accessor.get("normalized"), Some(JsonValue::Bool(true)));
+        let components = match accessor.get("type").and_then(JsonValue::as_str) {
+            Some("SCALAR") => 1,
+            Some("VEC2") => 2,
+            Some("VEC3") => 3,
+            Some("VEC4") => 4,
+            Some("MAT4") => 16,
+            other => return Err(ImportError::invalid(format!("Unsupported accessor type {:?}", other))),
+        };
+        let component_size = match component_type {
+            5120 | 5121 => 1,
+            5122 | 5123 => 2,
+            5125 | 5126 => 4,
+            other => return Err(ImportError::invalid(format!("Unsupported component type {}", other))),
+        };
+
+        let view = match optional_usize(accessor, "bufferView")? {
+            Some(view) => view,
+            // Accessors without a buffer view are filled with zeros
+            None if count <= MAX_UNBACKED_ACCESSOR_COUNT => return Ok(vec![vec![0.0; components]; count]),
+            None => return Err(ImportError::invalid(format!("Accessor {} without a buffer view is too large", index))),
+        };
+        let (data, stride) = self.buffer_view(view)?;
+        let offset = optional_usize(accessor, "byteOffset")?.unwrap_or(0);
+        let element_size = component_size * components;
+        let stride = stride.unwrap_or(element_size);
+        // Overlapping elements would let a tiny buffer view back any number of them
+        if stride < element_size {
+            return Err(ImportError::invalid(format!("Accessor {} has a stride shorter than its elements", index)));
+        }
+        let end = match count.checked_sub(1) {
+            Some(last) => stride
+                .checked_mul(last)
+                .and_then(|last| last.checked_add(offset))
+                .and_then(|start| start.checked_add(element_size)),
+            None => Some(0),
+        };
+        if !matches!(end, Some(end) if end <= data.len()) {
+            return Err(ImportError::invalid(format!("Accessor {} is out of range of its buffer view", index)));
+        }
+
+        let read = |position: usize| -> f64 {
+            let bytes = &data[position..position + component_size];
+            match component_type {
+                5120 => {
+                    let value = bytes[0] as i8 as f64;
+                    if normalized { (value / 127.0).max(-1.0) } else { value }
+                },
+                5121 => {
+                    let value = bytes[0] as f64;
+                    if normalized { value / 255.0 } else { value }
+                },
+                5122 => {
+                    let value = i16::from_le_bytes([bytes[0], bytes[1]]) as f64;
+                    if normalized { (value / 32767.0).max(-1.0) } else { value }
+                },
+                5123 => {
+                    let value = u16::from_le_bytes([bytes[0], bytes[1]]) as f64;
+                    if normalized { value / 65535.0 } else { value }
+                },
+                5125 => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+                _ => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            }
+        };
+
+        Ok((0..count)
+            .map(|element| {
+                let start = offset + element * stride;
+                (0..components).map(|component| read(start + component * component_size)).collect()
+            })
+            .collect())
+    }
+
+    fn textures(&self) -> Result<Vec<Rc<Texture>>, ImportError> {
+        let images = self.loader.array("images");
+        self.loader
+            .array("textures")
+            .iter()
+            .map(|texture| {
+                let source = required_usize(texture, "source", "texture")?;
+                let image = images
+                    .get(source)
+                    .ok_or_else(|| ImportError::invalid(format!("Image {} does not exist", source)))?;
+                let data = match (image.get("uri").and_then(JsonValue::as_str), optional_usize(image, "bufferView")?) {
+                    (Some(uri), _) => self.loader.external_file(uri)?,
+                    (None, Some(view)) => self.buffer_view(view)?.0.to_vec(),
+                    (None, None) => return Err(ImportError::invalid(format!("Image {} has no data", source))),
+                };
+                Texture::from_encoded(&data)
+                    .map(Rc::new)
+                    .map_err(|e| ImportError::invalid(format!("Cannot decode image {}: {}", source, e)))
+            })
+            .collect()
+    }
+
+    fn material(&self, material: &JsonValue, texture_count: usize) -> Result<GltfMaterial, ImportError> {
+        let pbr = material.get("pbrMetallicRoughness");
+        let factor = pbr
+            .and_then(|pbr| pbr.get("baseColorFactor"))
+            .map(|factor| numbers(factor, 4, "baseColorFactor"))
+            .transpose()?
+            .unwrap_or_else(|| vec![1.0; 4]);
+        let base_color_texture = match pbr.and_then(|pbr| pbr.get("baseColorTexture")) {
+            Some(texture) => Some(required_usize(texture, "index", "texture info")?),
+            None => None,
+        };
+        if base_color_texture.is_some_and(|texture| texture >= texture_count) {
+            return Err(ImportError::invalid("Material refers to a missing texture"));
+        }
+        Ok(GltfMaterial {
+            name: name_of(material),
+            base_color: color_from_unit_floats(factor[0], factor[1], factor[2]),
+            base_color_texture,
+        })
+    }
+
+    fn mesh(&self, mesh: &JsonValue, materials: &[GltfMaterial]) -> Result<GltfMesh, ImportError> {
+        let primitives = mesh.get("primitives").and_then(JsonValue::as_array).unwrap_or(&[]);
+        let mut result = Vec::with_capacity(primitives.len());
+        for primitive in primitives {
+            let mode = optional_usize(primitive, "mode")?.unwrap_or(MODE_TRIANGLES);
+            if ![MODE_TRIANGLES, MODE_TRIANGLE_STRIP, MODE_TRIANGLE_FAN].contains(&mode) {
+                // Points and lines cannot be rendered
+                continue;
+            }
+            let material = optional_usize(primitive, "material")?;
+            if material.is_some_and(|material| material >= materials.len()) {
+                return Err(ImportError::invalid("Primitive refers to a missing material"));
+            }
+            let base_color = material.map_or(RGB::new(255, 255, 255), |material| materials[material].base_color);
+            result.push(GltfPrimitive {mesh: self.primitive(primitive, mode, base_color)?, material});
+        }
+        Ok(GltfMesh {name: name_of(mesh), primitives: result})
+    }
+
+    fn primitive(&self, primitive: &JsonValue, mode: usize, base_color: RGB) -> Result<Mesh, ImportError> {
+        let attributes = primitive
+            .get("attributes")
+            .ok_or_else(|| ImportError::invalid("Primitive has no attributes"))?;
+        // Checking the type up front guarantees the width of the rows read from the accessor
+        let attribute = |name: &str, types: &[&str]| -> Result<Option<Vec<Vec<f64>>>, ImportError> {
+            let accessor = match optional_usize(attributes, name)? {
+                Some(accessor) => accessor,
+                None => return Ok(None),
+            };
+            let accessor_type = self.loader.array("accessors").get(accessor).and_then(|accessor| accessor.get("type"));
+            match accessor_type.and_then(JsonValue::as_str) {
+                Some(accessor_type) if !types.contains(&accessor_type) => Err(ImportError::invalid(format!(
+                    "Attribute {} has unsupported accessor type {}",
+                    name, accessor_type,
+                ))),
+                _ => self.accessor(accessor).map(Some),
+            }
+        };
+
+        let positions = attribute("POSITION", &["VEC3"])?;
+        let positions = positions.ok_or_else(|| ImportError::invalid("Primitive has no positions"))?;
+        let normals = attribute("NORMAL", &["VEC3"])?;
+        let uvs = attribute("TEXCOORD_0", &["VEC2"])?;
+        let colors = attribute("COLOR_0", &["VEC3", "VEC4"])?;
+
+        let mut mesh = Mesh::new();
+        for (index, position) in positions.iter().enumerate() {
+            let mut vertex = Vertex::new(Point3d {x: position[0], y: position[1], z: position[2]});
+            vertex.normal = normals.as_ref().and_then(|normals| normals.get(index)).map(|normal| {
+                Vector3d {x: normal[0], y: normal[1], z: normal[2]}
+            });
+            vertex.uv = uvs.as_ref().and_then(|uvs| uvs.get(index)).map(|uv| BasicPoint {x: uv[0], y: uv[1]});
+            let color = colors
+                .as_ref()
+                .and_then(|colors| colors.get(index))
+                .map_or(RGB::new(255, 255, 255), |color| color_from_unit_floats(color[0], color[1], color[2]));
+            vertex.color = color.modulate(base_color);
+            mesh.push_vertex(vertex);
+        }
+
+        let indices: Vec<usize> = match optional_usize(primitive, "indices")? {
+            Some(accessor) => self.accessor(accessor)?.iter().map(|row| row[0] as usize).collect(),
+            None => (0..positions.len()).collect(),
+        };
+        if indices.iter().any(|&index| index >= positions.len()) {
+            return Err(ImportError::invalid("Primitive index is out of range"));
+        }
+
+        match mode {
+            MODE_TRIANGLES => {
+                for triangle in indices.chunks_exact(3) {
+                    mesh.push_triangle(triangle[0], triangle[1], triangle[2]);
+                }
+            },
+            MODE_TRIANGLE_STRIP => {
+                for index in 2..indices.len() {
+                    // Every other triangle of a strip has the opposite winding
+                    if index % 2 == 0 {
+                        mesh.push_triangle(indices[index - 2], indices[index - 1], indices[index]);
+                    } else {
+                        mesh.push_triangle(indices[index - 1], indices[index - 2], indices[index]);
+                    }
+                }
+            },
+            _ => {
+                for index in 2..indices.len() {
+                    mesh.push_triangle(indices[0], indices[index - 1], indices[index]);
+                }
+            },
+        }
+        Ok(mesh)
+    }
+
+    fn node(&self, node: &JsonValue, mesh_count: usize, camera_count: usize) -> Result<GltfNode, ImportError> {
+        let transform = match node.get("matrix") {
+            Some(matrix) => {
+                let mut values = [0.0; 16];
+                values.copy_from_slice(&numbers(matrix, 16, "matrix")?);
+                Transform::from_matrix4d(&Matrix4d::from_column_major(&values))
+                    .ok_or_else(|| ImportError::invalid("Node matrix is not an affine transform"))?
+            },
+            None => {
+                let vector = |key, default: Vec<f64>| {
+                    node
+                        .get(key)
+                        .map(|value| numbers(value, default.len(), key))
+                        .transpose()
+                        .map(|value| value.unwrap_or(default))
+                };
+                let translation = vector("translation", vec![0.0, 0.0, 0.0])?;
+                let rotation = vector("rotation", vec![0.0, 0.0, 0.0, 1.0])?;
+                let scale = vector("scale", vec![1.0, 1.0, 1.0])?;
+                let vector3d = |values: Vec<f64>| Vector3d {x: values[0], y: values[1], z: values[2]};
+                let rotation = Quaternion {w: rotation[3], x: rotation[0], y: rotation[1], z: rotation[2]};
+                Transform::from_trs(vector3d(translation), rotation, vector3d(scale))
+            },
+        };
+
+        let children = match node.get("children").and_then(JsonValue::as_array) {
+            Some(children) => children
+                .iter()
+                .map(|child| child.as_usize().ok_or_else(|| ImportError::invalid("Invalid node child index")))
+                .collect::<Result<Vec<_>, _>>()?,
+            None => Vec::new(),
+        };
+        let mesh = optional_usize(node, "mesh")?;
+        let camera = optional_usize(node, "camera")?;
+        if mesh.is_some_and(|mesh| mesh >= mesh_count) || camera.is_some_and(|camera| camera >= camera_count) {
+            return Err(ImportError::invalid("Node refers to a missing mesh or camera"));
+        }
+        Ok(GltfNode {name: name_of(node), transform, children, mesh, camera})
+    }
+
+    fn roots(&self, nodes: &[GltfNode]) -> Result<Vec<usize>, ImportError> {
+        let scenes = self.loader.array("scenes");
+        let scene = optional_usize(self.loader.document, "scene")?.unwrap_or(0);
+        let roots = match scenes.get(scene).and_then(|scene| scene.get("nodes")).and_then(JsonValue::as_array) {
+            Some(roots) => {
+                let is_child = |root: usize| nodes.iter().any(|node| node.children.contains(&root));
+                roots
+                    .iter()
+                    .map(|root| root.as_usize().filter(|&root| root < nodes.len() && !is_child(root)))
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| ImportError::invalid("Invalid scene root node"))?
+            },
+            None => {
+                // Without scenes every node that is nobody's child is a root
+                let mut is_child = vec![false; nodes.len()];
+                for &child in nodes.iter().flat_map(|node| node.children.iter()) {
+                    is_child[child] = true;
+                }
+                (0..nodes.len()).filter(|&index| !is_child[index]).collect()
+            },
+        };
+        Ok(roots)
+    }
+}
+
+
+// Nodes must form a forest: children exist, no node has two parents, and there are no cycles
+fn validate_hierarchy(nodes: &[GltfNode]) -> Result<(), ImportError> {
+    let mut has_parent = vec![false; nodes.len()];
+    for &child in nodes.iter().flat_map(|node| node.children.iter()) {
+        match has_parent.get_mut(child) {
+            None => return Err(ImportError::invalid(format!("Node child index {} is out of range", child))),
+            Some(true) => return Err(ImportError::invalid(format!("Node {} has more than one parent", child))),
+            Some(has_parent) => *has_parent = true,
+        }
+    }
+
+    // With at most one parent per node, the nodes of a cycle cannot be reached from parentless nodes
+    let mut stack: Vec<usize> = (0..nodes.len()).filter(|&index| !has_parent[index]).collect();
+    let mut reached = 0;
+    while let Some(index) = stack.pop() {
+        reached += 1;
+        stack.extend_from_slice(&nodes[index].children);
+    }
+    if reached != nodes.len() {
+        return Err(ImportError::invalid("Node hierarchy contains a cycle"));
+    }
+    Ok(())
+}
+
+
+fn camera(camera: &JsonValue) -> Result<GltfCamera, ImportError> {
+    let name = name_of(camera);
+    match camera.get("type").and_then(JsonValue::as_str) {
+        Some("perspective") => {
+            let perspective = camera
+                .get("perspective")
+                .ok_or_else(|| ImportError::invalid("Perspective camera has no `perspective` object"))?;
+            let yfov = perspective
+                .get("yfov")
+                .and_then(JsonValue::as_f64)
+                .ok_or_else(|| ImportError::invalid("Perspective camera has no `yfov`"))?;
+            let aspect_ratio = perspective.get("aspectRatio").and_then(JsonValue::as_f64);
+            Ok(GltfCamera::Perspective {name, yfov: Angle::from_radians(yfov), aspect_ratio})
+        },
+        Some("orthographic") => Ok(GltfCamera::Orthographic {name}),
+        other => Err(ImportError::invalid(format!("Unsupported camera type {:?}", other))),
+    }
+}
+
+
+fn name_of(value: &JsonValue) -> String {
+    value.get("name").and_then(JsonValue::as_str).unwrap_or("").to_owned()
+}
+
+
+fn required_usize(value: &JsonValue, key: &str, what: &str) -> Result<usize, ImportError> {
+    optional_usize(value, key)?.ok_or_else(|| ImportError::invalid(format!("The {} has no `{}`", what, key)))
+}
+
+
+fn optional_usize(value: &JsonValue, key: &str) -> Result<Option<usize>, ImportError> {
+    match value.get(key) {
+        Some(field) => field
+            .as_usize()
+            .map(Some)
+            .ok_or_else(|| ImportError::invalid(format!("`{}` must be a non-negative integer", key))),
+        None => Ok(None),
+    }
+}
+
+
+fn numbers(value: &JsonValue, count: usize, key: &str) -> Result<Vec<f64>, ImportError> {
+    value
+        .as_array()
+        .filter(|items| items.len() == count)
+        .and_then(|items| items.iter().map(JsonValue::as_f64).collect())
+        .ok_or_else(|| ImportError::invalid(format!("`{}` must be an array of {} numbers", key, count)))
+}
+
+
+fn decode_base64(text: &str) -> Result<Vec<u8>, ImportError> {
+    let mut result = Vec::with_capacity(text.len() / 4 * 3);
+    let mut accumulator = 0u32;
+    let mut bits = 0;
+    for byte in text.bytes().filter(|&byte| byte != b'=' && !byte.is_ascii_whitespace()) {
+        let value = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            _ => return Err(ImportError::invalid("Invalid base64 data")),
+        };
+        accumulator = (accumulator << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            result.push((accumulator >> bits) as u8);
+        }
+    }
+    Ok(result)
+}
+
+
+fn percent_decode(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut result = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = bytes
+            .get(index + 1..index + 3)
+            .filter(|_| bytes[index] == b'%')
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(byte) => {
+                result.push(byte);
+                index += 3;
+            },
+            None => {
+                result.push(bytes[index]);
+                index += 1;
+            },
+        }
+    }
+    String::from_utf8_lossy(&result).into_owned()
+}
+
+
+// glTF is right-handed with Y pointing up, while the renderer's Y axis points down
+fn to_renderer_point(point: Point3d) -> Point3d {
+    Point3d {x: point.x, y: -point.y, z: point.z}
+}
+
+
+fn to_renderer_vector(vector: Vector3d) -> Vector3d {
+    Vector3d {x: vector.x, y: -vector.y, z: vector.z}
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Norm;
+
+    const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    fn data_uri(floats: &[f32]) -> String {
+        let bytes: Vec<u8> = floats.iter().flat_map(|float| float.to_le_bytes()).collect();
+        let mut result = String::from("data:application/octet-stream;base64,");
+        for chunk in bytes.chunks(3) {
+            let group = chunk
+                .iter()
+                .enumerate()
+                .fold(0u32, |group, (index, &byte)| group | (byte as u32) << (16 - 8 * index));
+            for index in 0..4 {
+                // A chunk of n bytes is written as n + 1 symbols and padded to four
+                let symbol = match index <= chunk.len() {
+                    true => BASE64[(group >> (18 - 6 * index) & 63) as usize],
+                    false => b'=',
+                };
+                result.push(symbol as char);
+            }
+        }
+        result
+    }
+
+    // A scene with one mesh made of `positions`, drawn with `mode` by `nodes`
+    fn scene(positions: &[f32], count: usize, mode: usize, nodes: &str) -> Result<GltfScene, ImportError> {
+        let source = format!(
+            r#"{{
+                "asset": {{"version": "2.0"}},
+                "buffers": [{{"uri": "{uri}", "byteLength": {length}}}],
+                "bufferViews": [{{"buffer": 0, "byteLength": {length}}}],
+                "accessors": [{{"bufferView": 0, "componentType": 5126, "count": {count}, "type": "VEC3"}}],
+                "meshes": [{{"primitives": [{{"attributes": {{"POSITION": 0}}, "mode": {mode}}}]}}],
+                "nodes": {nodes}
+            }}"#,
+            uri = data_uri(positions),
+            length = positions.len() * 4,
+            count = count,
+            mode = mode,
+            nodes = nodes,
+        );
+        parse_gltf(&source, None, None)
+    }
+
+    fn error_message(result: Result<GltfScene, ImportError>) -> String {
+        result.expect_err("The scene must be rejected").to_string()
+    }
+
+    const TRIANGLE: [f32; 9] = [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0];
+
+    #[test]
+    fn data_uri_buffers_are_decoded() {
+        let scene = scene(&TRIANGLE, 3, MODE_TRIANGLES, r#"[{"mesh": 0}]"#).unwrap();
+        let mesh = &scene.meshes[0].primitives[0].mesh;
+        assert_eq!(mesh.indices(), &[(0, 1, 2)]);
+        assert_eq!(mesh.vertices()[1].position, Point3d {x: 0.0, y: 1.0, z: 0.0});
+    }
+
+    #[test]
+    fn accessor_past_the_end_of_its_buffer_view_is_an_error() {
+        let message = error_message(scene(&TRIANGLE, 4, MODE_TRIANGLES, r#"[{"mesh": 0}]"#));
+        assert!(message.contains("Accessor 0 is out of range"), "{}", message);
+    }
+
+    #[test]
+    fn node_cycle_is_an_error() {
+        let message = error_message(scene(&TRIANGLE, 3, MODE_TRIANGLES, r#"[{"children": [1]}, {"children": [0]}]"#));
+        assert!(message.contains("cycle"), "{}", message);
+    }
+
+    #[test]
+    fn node_with_two_parents_is_an_error() {
+        let nodes = r#"[{"children": [2]}, {"children": [2]}, {"mesh": 0}]"#;
+        let message = error_message(scene(&TRIANGLE, 3, MODE_TRIANGLES, nodes));
+        assert!(message.contains("Node 2 has more than one parent"), "{}", message);
+    }
+
+    #[test]
+    fn triangle_strip_alternates_winding() {
+        let positions = [0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 2.0, 0.0, 0.0];
+        let scene = scene(&positions, 5, MODE_TRIANGLE_STRIP, r#"[{"mesh": 0}]"#).unwrap();
+        let mesh = &scene.meshes[0].primitives[0].mesh;
+        assert_eq!(mesh.indices(), &[(0, 1, 2), (2, 1, 3), (2, 3, 4)]);
+    }
+
+    #[test]
+    fn node_transforms_are_scaled_then_rotated_then_translated_under_their_parent() {
+        let half_sqrt = 0.5f64.sqrt();
+        let nodes = format!(
+            r#"[
+                {{"translation": [10, 0, 0], "children": [1]}},
+                {{"mesh": 0, "translation": [1, 2, 3], "rotation": [0, 0, {0}, {0}], "scale": [2, 2, 2]}}
+            ]"#,
+            half_sqrt,
+        );
+        let scene = scene(&TRIANGLE, 3, MODE_TRIANGLES, &nodes).unwrap();
+        let instances = scene.instances();
+        assert_eq!(instances.len(), 1);
+        assert_eq!(instances[0].node, 1);
+
+        // (1, 0, 0) is scaled to (2, 0, 0), turned about Z to (0, 2, 0) and moved to (11, 4, 3), then Y is flipped
+        let position = instances[0].mesh.vertices()[0].position;
+        let expected = Point3d {x: 11.0, y: -4.0, z: 3.0};
+        assert!((position - expected).norm() < 1e-9, "{:?}", position);
+    }
+}
@@ -0,0 +1,266 @@
+use super::ImportError;
+
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+
+// Arrays and objects are parsed recursively, so nesting is limited to keep malformed files from overflowing the stack
+const MAX_DEPTH: usize = 128;
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        match self {
+            JsonValue::Object(members) => members.iter().find(|(name, _)| name == key).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            JsonValue::Number(number) => Some(*number),
+            _ => None,
+        }
+    }
+
+    pub fn as_usize(&self) -> Option<usize> {
+        self.as_f64().filter(|number| *number >= 0.0 && number.fract() == 0.0).map(|number| number as usize)
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[JsonValue]> {
+        match self {
+            JsonValue::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+
+pub fn parse_json(source: &str) -> Result<JsonValue, ImportError> {
+    let mut parser = JsonParser {source, chars: source.char_indices().peekable(), depth: 0};
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    match parser.chars.peek() {
+        Some(&(offset, _)) => Err(parser.error_at(offset, "Unexpected trailing characters")),
+        None => Ok(value),
+    }
+}
+
+
+struct JsonParser<'a> {
+    source: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+    // Arrays and objects being parsed
+    depth: usize,
+}
+
+impl JsonParser<'_> {
+    fn error_at(&self, offset: usize, message: impl Into<String>) -> ImportError {
+        let line = self.source[..offset].matches('\n').count() + 1;
+        ImportError::parse(line, message)
+    }
+
+    fn error_here(&mut self, message: impl Into<String>) -> ImportError {
+        let offset = self.chars.peek().map_or(self.source.len(), |&(offset, _)| offset);
+        self.error_at(offset, message)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(&(_, ch)) = self.chars.peek() {
+            if !ch.is_ascii_whitespace() {
+                break;
+            }
+            self.chars.next();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), ImportError> {
+        self.skip_whitespace();
+        match self.chars.peek() {
+            Some(&(_, ch)) if ch == expected => {
+                self.chars.next();
+                Ok(())
+            },
+            _ => Err(self.error_here(format!("Expected `{}`", expected))),
+        }
+    }
+
+    fn value(&mut self) -> Result<JsonValue, ImportError> {
+        self.skip_whitespace();
+        match self.chars.peek().map(|&(_, ch)| ch) {
+            Some(ch @ ('{' | '[')) => {
+                if self.depth == MAX_DEPTH {
+                    return Err(self.error_here("Arrays and objects are nested too deeply"));
+                }
+                self.depth += 1;
+                let value = if ch == '{' { self.object() } else { self.array() };
+                self.depth -= 1;
+                value
+            },
+            Some('"') => self.string().map(JsonValue::String),
+            Some('t') => self.keyword("true", JsonValue::Bool(true)),
+            Some('f') => self.keyword("false", JsonValue::Bool(false)),
+            Some('n') => self.keyword("null", JsonValue::Null),
+            Some(ch) if ch == '-' || ch.is_ascii_digit() => self.number(),
+            _ => Err(self.error_here("Expected a value")),
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str, value: JsonValue) -> Result<JsonValue, ImportError> {
+        for expected in keyword.chars() {
+            match self.chars.next() {
+                Some((_, ch)) if ch == expected => {},
+                _ => return Err(self.error_here(format!("Expected `{}`", keyword))),
+            }
+        }
+        Ok(value)
+    }
+
+    fn number(&mut self) -> Result<JsonValue, ImportError> {
+        let start = self.chars.peek().map_or(self.source.len(), |&(offset, _)| offset);
+        let mut end = start;
+        while let Some(&(offset, ch)) = self.chars.peek() {
+            if !(ch.is_ascii_digit() || "+-.eE".contains(ch)) {
+                break;
+            }
+            end = offset + ch.len_utf8();
+            self.chars.next();
+        }
+        let text = &self.source[start..end];
+        text
+            .parse()
+            .map(JsonValue::Number)
+            .map_err(|_| self.error_at(start, format!("Invalid number `{}`", text)))
+    }
+
+    fn string(&mut self) -> Result<String, ImportError> {
+        self.expect('"')?;
+        let mut result = String::new();
+        loop {
+            let (offset, ch) = self.chars.next().ok_or_else(|| self.error_here("Unterminated string"))?;
+            match ch {
+                '"' => return Ok(result),
+                '\\' => {
+                    let (_, escaped) = self.chars.next().ok_or_else(|| self.error_here("Unterminated string"))?;
+                    result.push(match escaped {
+                        '"' => '"',
+                        '\\' => '\\',
+                        '/' => '/',
+                        'b' => '\u{8}',
+                        'f' => '\u{c}',
+                        'n' => '\n',
+                        'r' => '\r',
+                        't' => '\t',
+                        'u' => self.unicode_escape(offset)?,
+                        _ => return Err(self.error_at(offset, format!("Invalid escape `\\{}`", escaped))),
+                    });
+                },
+                _ => result.push(ch),
+            }
+        }
+    }
+
+    fn unicode_escape(&mut self, offset: usize) -> Result<char, ImportError> {
+        let first = self.hex4(offset)?;
+        let code = if (0xd800..0xdc00).contains(&first) {
+            // Surrogate pair
+            self.keyword("\\u", JsonValue::Null)?;
+            let second = self.hex4(offset)?;
+            0x10000 + ((first - 0xd800) << 10) + (second.wrapping_sub(0xdc00) & 0x3ff)
+        } else {
+            first
+        };
+        char::from_u32(code).ok_or_else(|| self.error_at(offset, "Invalid unicode escape"))
+    }
+
+    fn hex4(&mut self, offset: usize) -> Result<u32, ImportError> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let digit = self
+                .chars
+                .next()
+                .and_then(|(_, ch)| ch.to_digit(16))
+                .ok_or_else(|| self.error_at(offset, "Invalid unicode escape"))?;
+            code = code * 16 + digit;
+        }
+        Ok(code)
+    }
+
+    fn array(&mut self) -> Result<JsonValue, ImportError> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if let Some(&(_, ']')) = self.chars.peek() {
+            self.chars.next();
+            return Ok(JsonValue::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.chars.next() {
+                Some((_, ',')) => continue,
+                Some((_, ']')) => return Ok(JsonValue::Array(items)),
+                _ => return Err(self.error_here("Expected `,` or `]`")),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<JsonValue, ImportError> {
+        self.expect('{')?;
+        let mut members = Vec::new();
+        self.skip_whitespace();
+        if let Some(&(_, '}')) = self.chars.peek() {
+            self.chars.next();
+            return Ok(JsonValue::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.expect(':')?;
+            members.push((key, self.value()?));
+            self.skip_whitespace();
+            match self.chars.next() {
+                Some((_, ',')) => continue,
+                Some((_, '}')) => return Ok(JsonValue::Object(members)),
+                _ => return Err(self.error_here("Expected `,` or `}`")),
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nested(depth: usize) -> String {
+        "[".repeat(depth) + &"]".repeat(depth)
+    }
+
+    #[test]
+    fn nesting_up_to_the_limit_is_parsed() {
+        assert!(parse_json(&nested(MAX_DEPTH)).is_ok());
+    }
+
+    #[test]
+    fn deeper_nesting_is_an_error() {
+        assert!(matches!(parse_json(&nested(100_000)), Err(ImportError::Parse {line: 1, ..})));
+    }
+}
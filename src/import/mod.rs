@@ -0,0 +1,192 @@
+pub mod gltf;
+pub mod obj;
+pub mod ply;
+pub mod stl;
+pub mod svg;
+
+mod json;
+
+use crate::geometry::{Cross, Normalize, Point3d, Triangle3d, Vector3d};
+use crate::render::RGB;
+
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::io;
+use std::path::{Path, PathBuf};
+
+
+#[derive(Debug)]
+pub enum ImportError {
+    Io(io::Error),
+    Parse {
+        file: Option<PathBuf>,
+        line: usize,
+        message: String,
+    },
+    Invalid {
+        file: Option<PathBuf>,
+        message: String,
+    },
+}
+
+impl ImportError {
+    pub fn parse(line: usize, message: impl Into<String>) -> ImportError {
+        ImportError::Parse {file: None, line, message: message.into()}
+    }
+
+    pub fn invalid(message: impl Into<String>) -> ImportError {
+        ImportError::Invalid {file: None, message: message.into()}
+    }
+
+    pub fn in_file(self, path: &Path) -> ImportError {
+        match self {
+            ImportError::Parse {file: None, line, message} => {
+                ImportError::Parse {file: Some(path.to_owned()), line, message}
+            },
+            ImportError::Invalid {file: None, message} => ImportError::Invalid {file: Some(path.to_owned()), message},
+            other => other,
+        }
+    }
+}
+
+impl From<io::Error> for ImportError {
+    fn from(error: io::Error) -> ImportError {
+        ImportError::Io(error)
+    }
+}
+
+impl Display for ImportError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Io(error) => write!(f, "I/O error: {}", error),
+            ImportError::Parse {file: Some(file), line, message} => {
+                write!(f, "Parse error at {}:{}: {}", file.display(), line, message)
+            },
+            ImportError::Parse {file: None, line, message} => write!(f, "Parse error at line {}: {}", line, message),
+            ImportError::Invalid {file: Some(file), message} => {
+                write!(f, "Invalid file {}: {}", file.display(), message)
+            },
+            ImportError::Invalid {file: None, message} => write!(f, "Invalid file: {}", message),
+        }
+    }
+}
+
+impl Error for ImportError {}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Material {
+    pub name: String,
+    pub ambient: RGB,
+    pub diffuse: RGB,
+    pub specular: RGB,
+    pub shininess: f64,
+    pub opacity: f64,
+    pub diffuse_texture: Option<PathBuf>,
+}
+
+impl Material {
+    pub fn new(name: String) -> Material {
+        Material {
+            name,
+            ambient: RGB::new(0, 0, 0),
+            diffuse: RGB::new(255, 255, 255),
+            specular: RGB::new(0, 0, 0),
+            shininess: 0.0,
+            opacity: 1.0,
+            diffuse_texture: None,
+        }
+    }
+}
+
+
+fn color_from_unit_floats(r: f64, g: f64, b: f64) -> RGB {
+    let channel = |x: f64| (x.clamp(0.0, 1.0) * 255.0).round() as u8;
+    RGB::new(channel(r), channel(g), channel(b))
+}
+
+
+fn face_normal(tri: &Triangle3d) -> Vector3d {
+    let normal = (tri.b - tri.a).cross(&(tri.c - tri.a));
+    normal.normalized().unwrap_or(normal)
+}
+
+
+fn is_zero_vector(vector: Vector3d) -> bool {
+    vector.x == 0.0 && vector.y == 0.0 && vector.z == 0.0
+}
+
+
+// Returns `None` for degenerate triangles, which `Triangle3d::new` would reject
+fn checked_triangle(a: Point3d, b: Point3d, c: Point3d) -> Option<Triangle3d> {
+    if [a, b, c].iter().any(|p| !(p.x.is_finite() && p.y.is_finite() && p.z.is_finite())) {
+        return None;
+    }
+    Triangle3d::try_new(a, b, c)
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Endianness {
+    Little,
+    Big,
+}
+
+
+struct ByteReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+    endianness: Endianness,
+}
+
+impl<'a> ByteReader<'a> {
+    fn new(data: &'a [u8], endianness: Endianness) -> ByteReader<'a> {
+        ByteReader {data, offset: 0, endianness}
+    }
+
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], ImportError> {
+        let end = self.offset + N;
+        if end > self.data.len() {
+            return Err(ImportError::invalid(format!("Unexpected end of data at byte {}", self.offset)));
+        }
+        let mut bytes = [0; N];
+        bytes.copy_from_slice(&self.data[self.offset..end]);
+        self.offset = end;
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, count: usize) -> Result<(), ImportError> {
+        if self.offset + count > self.data.len() {
+            return Err(ImportError::invalid(format!("Unexpected end of data at byte {}", self.offset)));
+        }
+        self.offset += count;
+        Ok(())
+    }
+}
+
+macro_rules! byte_reader_methods {
+    ($($name:ident -> $type:ty;)*) => {
+        impl ByteReader<'_> {
+            $(
+                fn $name(&mut self) -> Result<$type, ImportError> {
+                    let bytes = self.take()?;
+                    Ok(match self.endianness {
+                        Endianness::Little => <$type>::from_le_bytes(bytes),
+                        Endianness::Big => <$type>::from_be_bytes(bytes),
+                    })
+                }
+            )*
+        }
+    };
+}
+
+byte_reader_methods! {
+    read_i8 -> i8;
+    read_u8 -> u8;
+    read_i16 -> i16;
+    read_u16 -> u16;
+    read_i32 -> i32;
+    read_u32 -> u32;
+    read_f32 -> f32;
+    read_f64 -> f64;
+}
@@ -0,0 +1,401 @@
+use super::{ImportError, Material, checked_triangle, color_from_unit_floats};
+use crate::geometry::{BasicPoint, Point3d, Vector3d};
+use crate::mesh::{Mesh, Vertex};
+use crate::polygon::triangulate_face;
+use crate::render::RGB;
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+use std::str::SplitWhitespace;
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjGroup {
+    pub name: String,
+    pub material: Option<String>,
+    // Range of triangles in `ObjModel::mesh.indices`
+    pub triangles: Range<usize>,
+}
+
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ObjModel {
+    pub mesh: Mesh,
+    pub groups: Vec<ObjGroup>,
+    pub material_libraries: Vec<String>,
+    pub materials: HashMap<String, Material>,
+    // Lines of faces which were skipped because they are degenerate or intersect themselves
+    pub skipped_faces: Vec<usize>,
+}
+
+impl ObjModel {
+    pub fn apply_materials(&mut self) {
+        let ObjModel {mesh, groups, materials, ..} = self;
+        for group in groups.iter() {
+            let material = group.material.as_ref().and_then(|name| materials.get(name));
+            if let Some(material) = material {
+                for triangle in group.triangles.clone() {
+                    let (i, j, k) = mesh.indices()[triangle];
+                    for &index in &[i, j, k] {
+                        mesh.vertices_mut()[index].color = material.diffuse;
+                    }
+                }
+            }
+        }
+    }
+}
+
+
+pub fn load_obj(path: impl AsRef<Path>) -> Result<ObjModel, ImportError> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path)?;
+    let mut model = parse_obj(&source).map_err(|e| e.in_file(path))?;
+
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+    for library in &model.material_libraries {
+        let library_path = directory.join(library);
+        let mut materials = match load_mtl(&library_path) {
+            Ok(materials) => materials,
+            // Faces using the missing materials keep the default colour
+            Err(ImportError::Io(error)) if error.kind() == io::ErrorKind::NotFound => {
+                println!("Material library {} is missing, using the default material", library_path.display());
+                continue;
+            },
+            Err(error) => return Err(error),
+        };
+        for material in materials.values_mut() {
+            material.diffuse_texture = material.diffuse_texture.as_ref().map(|texture| directory.join(texture));
+        }
+        model.materials.extend(materials);
+    }
+    model.apply_materials();
+    Ok(model)
+}
+
+
+pub fn load_mtl(path: impl AsRef<Path>) -> Result<HashMap<String, Material>, ImportError> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path)?;
+    parse_mtl(&source).map_err(|e| e.in_file(path))
+}
+
+
+pub fn parse_obj(source: &str) -> Result<ObjModel, ImportError> {
+    let mut parser = ObjParser::new();
+    for (line_index, line) in source.lines().enumerate() {
+        parser.line = line_index + 1;
+        parser.parse_line(line)?;
+    }
+    Ok(parser.finish())
+}
+
+
+pub fn parse_mtl(source: &str) -> Result<HashMap<String, Material>, ImportError> {
+    let mut materials = HashMap::new();
+    let mut current: Option<Material> = None;
+
+    for (line_index, line) in source.lines().enumerate() {
+        let line_number = line_index + 1;
+        let mut tokens = Tokens::new(strip_comment(line), line_number);
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+
+        if keyword == "newmtl" {
+            let name = tokens.rest().ok_or_else(|| ImportError::parse(line_number, "Missing material name"))?;
+            if let Some(material) = current.take() {
+                materials.insert(material.name.clone(), material);
+            }
+            current = Some(Material::new(name.to_owned()));
+            continue;
+        }
+
+        let material = current
+            .as_mut()
+            .ok_or_else(|| ImportError::parse(line_number, format!("`{}` before any `newmtl`", keyword)))?;
+        match keyword {
+            "Ka" => material.ambient = tokens.color()?,
+            "Kd" => material.diffuse = tokens.color()?,
+            "Ks" => material.specular = tokens.color()?,
+            "Ns" => material.shininess = tokens.float()?,
+            "d" => material.opacity = tokens.float()?,
+            "Tr" => material.opacity = 1.0 - tokens.float()?,
+            "map_Kd" => {
+                // Texture options are not supported, the file name is always the last token
+                let file = tokens.last().ok_or_else(|| ImportError::parse(line_number, "Missing texture file name"))?;
+                material.diffuse_texture = Some(file.into());
+            },
+            _ => {},
+        }
+    }
+
+    if let Some(material) = current {
+        materials.insert(material.name.clone(), material);
+    }
+    Ok(materials)
+}
+
+
+fn strip_comment(line: &str) -> &str {
+    match line.find('#') {
+        Some(position) => &line[..position],
+        None => line,
+    }
+}
+
+
+struct Tokens<'a> {
+    line: &'a str,
+    tokens: SplitWhitespace<'a>,
+    line_number: usize,
+}
+
+impl<'a> Tokens<'a> {
+    fn new(line: &'a str, line_number: usize) -> Tokens<'a> {
+        Tokens {line, tokens: line.split_whitespace(), line_number}
+    }
+
+    fn rest(&mut self) -> Option<&'a str> {
+        let first = self.tokens.next()?;
+        let offset = first.as_ptr() as usize - self.line.as_ptr() as usize;
+        Some(self.line[offset..].trim())
+    }
+
+    fn float(&mut self) -> Result<f64, ImportError> {
+        let token = self
+            .tokens
+            .next()
+            .ok_or_else(|| ImportError::parse(self.line_number, "Expected a number"))?;
+        token
+            .parse()
+            .map_err(|_| ImportError::parse(self.line_number, format!("Invalid number `{}`", token)))
+    }
+
+    fn optional_float(&mut self) -> Result<Option<f64>, ImportError> {
+        match self.tokens.clone().next() {
+            Some(_) => self.float().map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn color(&mut self) -> Result<RGB, ImportError> {
+        let r = self.float()?;
+        let g = self.optional_float()?.unwrap_or(r);
+        let b = self.optional_float()?.unwrap_or(r);
+        Ok(color_from_unit_floats(r, g, b))
+    }
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        self.tokens.next()
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct VertexKey {
+    position: usize,
+    uv: Option<usize>,
+    normal: Option<usize>,
+}
+
+
+struct ObjParser {
+    line: usize,
+    positions: Vec<Point3d>,
+    uvs: Vec<BasicPoint<f64>>,
+    normals: Vec<Vector3d>,
+    vertex_cache: HashMap<(VertexKey, Option<String>), usize>,
+    model: ObjModel,
+    group_name: String,
+    group_material: Option<String>,
+    group_start: usize,
+}
+
+impl ObjParser {
+    fn new() -> ObjParser {
+        ObjParser {
+            line: 0,
+            positions: Vec::new(),
+            uvs: Vec::new(),
+            normals: Vec::new(),
+            vertex_cache: HashMap::new(),
+            model: ObjModel::default(),
+            group_name: String::from("default"),
+            group_material: None,
+            group_start: 0,
+        }
+    }
+
+    fn parse_line(&mut self, line: &str) -> Result<(), ImportError> {
+        let mut tokens = Tokens::new(strip_comment(line), self.line);
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => return Ok(()),
+        };
+
+        match keyword {
+            "v" => {
+                let position = Point3d {x: tokens.float()?, y: tokens.float()?, z: tokens.float()?};
+                let w = tokens.optional_float()?.unwrap_or(1.0);
+                if w == 0.0 {
+                    return Err(ImportError::parse(self.line, "Vertex has a zero `w` coordinate"));
+                }
+                self.positions.push(Point3d {x: position.x / w, y: position.y / w, z: position.z / w});
+            },
+            "vt" => {
+                let u = tokens.float()?;
+                let v = tokens.optional_float()?.unwrap_or(0.0);
+                // OBJ puts the UV origin at the bottom left corner of the texture
+                self.uvs.push(BasicPoint {x: u, y: 1.0 - v});
+            },
+            "vn" => self.normals.push(Vector3d {x: tokens.float()?, y: tokens.float()?, z: tokens.float()?}),
+            "f" => self.parse_face(tokens)?,
+            "g" | "o" => {
+                let name = tokens.rest().unwrap_or("default").to_owned();
+                self.start_group(name, self.group_material.clone());
+            },
+            "usemtl" => {
+                let material = tokens.rest().ok_or_else(|| ImportError::parse(self.line, "Missing material name"))?;
+                self.start_group(self.group_name.clone(), Some(material.to_owned()));
+            },
+            "mtllib" => {
+                self.model.material_libraries.extend(tokens.map(str::to_owned));
+            },
+            // Smoothing groups, lines, points and free-form geometry are ignored
+            _ => {},
+        }
+        Ok(())
+    }
+
+    fn parse_face(&mut self, tokens: Tokens) -> Result<(), ImportError> {
+        let corners = tokens
+            .map(|token| self.parse_face_vertex(token))
+            .collect::<Result<Vec<_>, _>>()?;
+        if corners.len() < 3 {
+            return Err(ImportError::parse(self.line, "A face must have at least three vertices"));
+        }
+
+        let ring: Vec<Point3d> = corners.iter().map(|&index| self.model.mesh.vertices()[index].position).collect();
+        if let [a, b, c] = ring[..] {
+            match checked_triangle(a, b, c) {
+                Some(_) => self.model.mesh.push_triangle(corners[0], corners[1], corners[2]),
+                None => self.model.skipped_faces.push(self.line),
+            }
+            return Ok(());
+        }
+        match triangulate_face(&ring) {
+            Some(triangles) => {
+                for (a, b, c) in triangles {
+                    self.model.mesh.push_triangle(corners[a], corners[b], corners[c]);
+                }
+            },
+            None => self.model.skipped_faces.push(self.line),
+        }
+        Ok(())
+    }
+
+    fn parse_face_vertex(&mut self, token: &str) -> Result<usize, ImportError> {
+        let mut parts = token.split('/');
+        let position = parts.next().unwrap_or("");
+        let uv = parts.next().filter(|part| !part.is_empty());
+        let normal = parts.next().filter(|part| !part.is_empty());
+        if parts.next().is_some() {
+            return Err(ImportError::parse(self.line, format!("Invalid face vertex `{}`", token)));
+        }
+
+        let key = VertexKey {
+            position: self.resolve_index(position, self.positions.len())?,
+            uv: uv.map(|uv| self.resolve_index(uv, self.uvs.len())).transpose()?,
+            normal: normal.map(|normal| self.resolve_index(normal, self.normals.len())).transpose()?,
+        };
+        let cache_key = (key, self.group_material.clone());
+        if let Some(&index) = self.vertex_cache.get(&cache_key) {
+            return Ok(index);
+        }
+
+        let vertex = Vertex {
+            normal: key.normal.map(|index| self.normals[index]),
+            uv: key.uv.map(|index| self.uvs[index]),
+            ..Vertex::new(self.positions[key.position])
+        };
+        let index = self.model.mesh.push_vertex(vertex);
+        self.vertex_cache.insert(cache_key, index);
+        Ok(index)
+    }
+
+    // OBJ indices are 1-based, negative ones count from the end
+    fn resolve_index(&self, token: &str, count: usize) -> Result<usize, ImportError> {
+        let index: i64 = token
+            .parse()
+            .map_err(|_| ImportError::parse(self.line, format!("Invalid index `{}`", token)))?;
+        let resolved = if index > 0 {
+            index - 1
+        } else {
+            count as i64 + index
+        };
+        if index == 0 || resolved < 0 || resolved >= count as i64 {
+            Err(ImportError::parse(self.line, format!("Index {} is out of range", index)))
+        } else {
+            Ok(resolved as usize)
+        }
+    }
+
+    fn start_group(&mut self, name: String, material: Option<String>) {
+        self.finish_group();
+        self.group_name = name;
+        self.group_material = material;
+    }
+
+    fn finish_group(&mut self) {
+        let end = self.model.mesh.triangle_count();
+        if end > self.group_start {
+            self.model.groups.push(ObjGroup {
+                name: self.group_name.clone(),
+                material: self.group_material.clone(),
+                triangles: self.group_start..end,
+            });
+        }
+        self.group_start = end;
+    }
+
+    fn finish(mut self) -> ObjModel {
+        self.finish_group();
+        self.model
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn degenerate_triangles_are_skipped() {
+        let source = "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 2 0 0\nf 1 2 3\nf 1 2 4\nf 1 1 3\n";
+        let model = parse_obj(source).unwrap();
+        assert_eq!(model.mesh.indices(), &[(0, 1, 2)]);
+        assert_eq!(model.skipped_faces, [6, 7]);
+    }
+
+    #[test]
+    fn missing_material_library_falls_back_to_the_default_material() {
+        let directory = std::env::temp_dir().join(format!("obj-missing-mtl-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("model.obj");
+        fs::write(&path, "mtllib missing.mtl\nusemtl red\nv 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n").unwrap();
+        let model = load_obj(&path);
+        fs::remove_dir_all(&directory).unwrap();
+
+        let model = model.unwrap();
+        assert!(model.materials.is_empty());
+        assert_eq!(model.mesh.vertices()[0].color, Vertex::new(Point3d {x: 0.0, y: 0.0, z: 0.0}).color);
+    }
+}
@@ -0,0 +1,418 @@
+use super::{ByteReader, Endianness, ImportError, checked_triangle, color_from_unit_floats};
+use crate::geometry::{BasicPoint, Point3d, Vector3d};
+use crate::mesh::{Mesh, Vertex};
+use crate::polygon::triangulate_face;
+use crate::render::RGB;
+
+use std::fs;
+use std::path::Path;
+
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlyModel {
+    pub mesh: Mesh,
+    pub comments: Vec<String>,
+    // Indices of faces which were skipped (entirely or partially) because they are degenerate
+    pub skipped_faces: Vec<usize>,
+}
+
+
+pub fn load_ply(path: impl AsRef<Path>) -> Result<PlyModel, ImportError> {
+    let path = path.as_ref();
+    let data = fs::read(path)?;
+    parse_ply(&data).map_err(|e| e.in_file(path))
+}
+
+
+pub fn parse_ply(data: &[u8]) -> Result<PlyModel, ImportError> {
+    let (header, body_offset) = parse_header(data)?;
+    let body = &data[body_offset..];
+    let elements = match header.format {
+        Format::Ascii => {
+            let source = std::str::from_utf8(body).map_err(|_| ImportError::invalid("ASCII PLY is not valid UTF-8"))?;
+            read_ascii_elements(&header, source)?
+        },
+        Format::Binary(endianness) => read_binary_elements(&header, body, endianness)?,
+    };
+    build_model(&header, elements)
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Ascii,
+    Binary(Endianness),
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ScalarType {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl ScalarType {
+    fn parse(name: &str) -> Option<ScalarType> {
+        match name {
+            "char" | "int8" => Some(ScalarType::I8),
+            "uchar" | "uint8" => Some(ScalarType::U8),
+            "short" | "int16" => Some(ScalarType::I16),
+            "ushort" | "uint16" => Some(ScalarType::U16),
+            "int" | "int32" => Some(ScalarType::I32),
+            "uint" | "uint32" => Some(ScalarType::U32),
+            "float" | "float32" => Some(ScalarType::F32),
+            "double" | "float64" => Some(ScalarType::F64),
+            _ => None,
+        }
+    }
+
+    fn is_integer(self) -> bool {
+        !matches!(self, ScalarType::F32 | ScalarType::F64)
+    }
+
+    fn read(self, reader: &mut ByteReader) -> Result<f64, ImportError> {
+        Ok(match self {
+            ScalarType::I8 => reader.read_i8()? as f64,
+            ScalarType::U8 => reader.read_u8()? as f64,
+            ScalarType::I16 => reader.read_i16()? as f64,
+            ScalarType::U16 => reader.read_u16()? as f64,
+            ScalarType::I32 => reader.read_i32()? as f64,
+            ScalarType::U32 => reader.read_u32()? as f64,
+            ScalarType::F32 => reader.read_f32()? as f64,
+            ScalarType::F64 => reader.read_f64()?,
+        })
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+enum PropertyKind {
+    Scalar(ScalarType),
+    List {count: ScalarType, item: ScalarType},
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+struct Property {
+    name: String,
+    kind: PropertyKind,
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+    // Line of the `element` declaration, for error reporting
+    line: usize,
+}
+
+impl Element {
+    fn property_index(&self, names: &[&str]) -> Option<usize> {
+        self.properties.iter().position(|property| names.contains(&property.name.as_str()))
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+struct Header {
+    format: Format,
+    elements: Vec<Element>,
+    comments: Vec<String>,
+    // Number of lines in the header, so that ASCII body lines are reported correctly
+    line_count: usize,
+}
+
+
+// Values of one element instance, one entry per property (scalars are one-element lists)
+type Record = Vec<Vec<f64>>;
+
+
+fn parse_header(data: &[u8]) -> Result<(Header, usize), ImportError> {
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    let mut comments = Vec::new();
+    let mut offset = 0;
+    let mut line_number = 0;
+
+    loop {
+        let line_end = data[offset..]
+            .iter()
+            .position(|&byte| byte == b'\n')
+            .ok_or_else(|| ImportError::parse(line_number + 1, "Header is not terminated by `end_header`"))?;
+        let line_bytes = &data[offset..offset + line_end];
+        offset += line_end + 1;
+        line_number += 1;
+
+        let line = std::str::from_utf8(line_bytes)
+            .map_err(|_| ImportError::parse(line_number, "Header is not valid UTF-8"))?
+            .trim();
+        let mut tokens = line.split_whitespace();
+        let keyword = tokens.next().unwrap_or("");
+
+        if line_number == 1 {
+            if keyword != "ply" {
+                return Err(ImportError::parse(line_number, "Missing `ply` magic number"));
+            }
+            continue;
+        }
+
+        match keyword {
+            "format" => {
+                format = Some(match tokens.next() {
+                    Some("ascii") => Format::Ascii,
+                    Some("binary_little_endian") => Format::Binary(Endianness::Little),
+                    Some("binary_big_endian") => Format::Binary(Endianness::Big),
+                    other => {
+                        let message = format!("Unsupported format `{}`", other.unwrap_or(""));
+                        return Err(ImportError::parse(line_number, message));
+                    },
+                });
+            },
+            "comment" | "obj_info" => comments.push(line[keyword.len()..].trim().to_owned()),
+            "element" => {
+                let name = tokens.next().ok_or_else(|| ImportError::parse(line_number, "Missing element name"))?;
+                let count = tokens
+                    .next()
+                    .and_then(|token| token.parse().ok())
+                    .ok_or_else(|| ImportError::parse(line_number, "Missing or invalid element count"))?;
+                elements.push(Element {name: name.to_owned(), count, properties: Vec::new(), line: line_number});
+            },
+            "property" => {
+                let element = elements
+                    .last_mut()
+                    .ok_or_else(|| ImportError::parse(line_number, "`property` before any `element`"))?;
+                let scalar = |token: Option<&str>| {
+                    token
+                        .and_then(ScalarType::parse)
+                        .ok_or_else(|| ImportError::parse(line_number, "Missing or unknown property type"))
+                };
+                let kind = match tokens.next() {
+                    Some("list") => {
+                        let count = scalar(tokens.next())?;
+                        if !count.is_integer() {
+                            return Err(ImportError::parse(line_number, "List count type must be an integer"));
+                        }
+                        PropertyKind::List {count, item: scalar(tokens.next())?}
+                    },
+                    other => PropertyKind::Scalar(scalar(other)?),
+                };
+                let name = tokens.next().ok_or_else(|| ImportError::parse(line_number, "Missing property name"))?;
+                element.properties.push(Property {name: name.to_owned(), kind});
+            },
+            "end_header" => break,
+            "" => {},
+            _ => return Err(ImportError::parse(line_number, format!("Unexpected keyword `{}`", keyword))),
+        }
+    }
+
+    let format = format.ok_or_else(|| ImportError::parse(line_number, "Missing `format` line"))?;
+    Ok((Header {format, elements, comments, line_count: line_number}, offset))
+}
+
+
+fn read_ascii_elements(header: &Header, source: &str) -> Result<Vec<Vec<Record>>, ImportError> {
+    let mut lines = source
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (header.line_count + index + 1, line))
+        .filter(|(_, line)| !line.trim().is_empty());
+    let mut result = Vec::with_capacity(header.elements.len());
+
+    for element in &header.elements {
+        // The count comes from the header, so it is not trusted for preallocation
+        let mut records = Vec::new();
+        for _ in 0..element.count {
+            let (line_number, line) = lines.next().ok_or_else(|| {
+                ImportError::parse(element.line, format!("Not enough `{}` elements in the body", element.name))
+            })?;
+            let mut tokens = line.split_whitespace();
+            let mut next = || -> Result<f64, ImportError> {
+                let token = tokens.next().ok_or_else(|| ImportError::parse(line_number, "Too few values"))?;
+                token
+                    .parse()
+                    .map_err(|_| ImportError::parse(line_number, format!("Invalid number `{}`", token)))
+            };
+
+            let mut record = Vec::with_capacity(element.properties.len());
+            for property in &element.properties {
+                match property.kind {
+                    PropertyKind::Scalar(_) => record.push(vec![next()?]),
+                    PropertyKind::List {..} => {
+                        let count = next()?;
+                        if count < 0.0 || count.fract() != 0.0 {
+                            return Err(ImportError::parse(line_number, format!("Invalid list length {}", count)));
+                        }
+                        record.push((0..count as usize).map(|_| next()).collect::<Result<_, _>>()?);
+                    },
+                }
+            }
+            if tokens.next().is_some() {
+                return Err(ImportError::parse(line_number, "Too many values"));
+            }
+            records.push(record);
+        }
+        result.push(records);
+    }
+    Ok(result)
+}
+
+
+fn read_binary_elements(
+    header: &Header,
+    body: &[u8],
+    endianness: Endianness
+) -> Result<Vec<Vec<Record>>, ImportError> {
+    let mut reader = ByteReader::new(body, endianness);
+    let mut result = Vec::with_capacity(header.elements.len());
+
+    for element in &header.elements {
+        // Records without properties take no bytes, so nothing would bound their count
+        if element.properties.is_empty() && element.count > 0 {
+            let message = format!("Element `{}` has no properties but a count of {}", element.name, element.count);
+            return Err(ImportError::parse(element.line, message));
+        }
+        let mut records = Vec::new();
+        for _ in 0..element.count {
+            let mut record = Vec::with_capacity(element.properties.len());
+            for property in &element.properties {
+                match property.kind {
+                    PropertyKind::Scalar(scalar) => record.push(vec![scalar.read(&mut reader)?]),
+                    PropertyKind::List {count, item} => {
+                        let count = count.read(&mut reader)?;
+                        if count < 0.0 {
+                            return Err(ImportError::invalid(format!("Negative list length {}", count)));
+                        }
+                        record.push((0..count as usize).map(|_| item.read(&mut reader)).collect::<Result<_, _>>()?);
+                    },
+                }
+            }
+            records.push(record);
+        }
+        result.push(records);
+    }
+    Ok(result)
+}
+
+
+fn build_model(header: &Header, elements: Vec<Vec<Record>>) -> Result<PlyModel, ImportError> {
+    let mut model = PlyModel {comments: header.comments.clone(), ..PlyModel::default()};
+
+    let vertex_element = header.elements.iter().position(|element| element.name == "vertex");
+    if let Some(element_index) = vertex_element {
+        let element = &header.elements[element_index];
+        let column = |names: &[&str]| element.property_index(names);
+        let position = (column(&["x"]), column(&["y"]), column(&["z"]));
+        let (x, y, z) = match position {
+            (Some(x), Some(y), Some(z)) => (x, y, z),
+            _ => return Err(ImportError::parse(element.line, "Vertices must have `x`, `y` and `z` properties")),
+        };
+        let normal = (column(&["nx"]), column(&["ny"]), column(&["nz"]));
+        let color = (column(&["red", "r"]), column(&["green", "g"]), column(&["blue", "b"]));
+        let uv = (column(&["s", "u", "texture_u"]), column(&["t", "v", "texture_v"]));
+        let color_is_float = color.0.is_some_and(|index| {
+            matches!(element.properties[index].kind, PropertyKind::Scalar(ScalarType::F32 | ScalarType::F64))
+        });
+
+        for record in &elements[element_index] {
+            let value = |index: usize| record[index].first().copied().unwrap_or(0.0);
+            let mut vertex = Vertex::new(Point3d {x: value(x), y: value(y), z: value(z)});
+            if let (Some(nx), Some(ny), Some(nz)) = normal {
+                vertex.normal = Some(Vector3d {x: value(nx), y: value(ny), z: value(nz)});
+            }
+            if let (Some(r), Some(g), Some(b)) = color {
+                vertex.color = if color_is_float {
+                    color_from_unit_floats(value(r), value(g), value(b))
+                } else {
+                    let channel = |index| value(index).clamp(0.0, 255.0) as u8;
+                    RGB::new(channel(r), channel(g), channel(b))
+                };
+            }
+            if let (Some(u), Some(v)) = uv {
+                // Like OBJ, PLY puts the UV origin at the bottom left corner of the texture
+                vertex.uv = Some(BasicPoint {x: value(u), y: 1.0 - value(v)});
+            }
+            model.mesh.push_vertex(vertex);
+        }
+    }
+
+    let face_element = header.elements.iter().position(|element| element.name == "face");
+    if let Some(element_index) = face_element {
+        let element = &header.elements[element_index];
+        let indices = element
+            .property_index(&["vertex_indices", "vertex_index"])
+            .ok_or_else(|| ImportError::parse(element.line, "Faces must have a `vertex_indices` property"))?;
+
+        for (face_index, record) in elements[element_index].iter().enumerate() {
+            let corners = &record[indices];
+            let vertex_count = model.mesh.vertices().len();
+            if corners.iter().any(|&index| index < 0.0 || index as usize >= vertex_count) {
+                let message = format!("Face {} refers to a missing vertex", face_index);
+                return Err(ImportError::parse(element.line, message));
+            }
+            if corners.len() < 3 {
+                model.skipped_faces.push(face_index);
+                continue;
+            }
+
+            let corners: Vec<usize> = corners.iter().map(|&index| index as usize).collect();
+            let ring: Vec<Point3d> = corners.iter().map(|&index| model.mesh.vertices()[index].position).collect();
+            let triangles = match ring.len() {
+                3 => vec![(0, 1, 2)],
+                _ => match triangulate_face(&ring) {
+                    Some(triangles) => triangles,
+                    None => {
+                        model.skipped_faces.push(face_index);
+                        continue;
+                    },
+                },
+            };
+            let mut skipped = false;
+            for (a, b, c) in triangles {
+                if checked_triangle(ring[a], ring[b], ring[c]).is_some() {
+                    model.mesh.push_triangle(corners[a], corners[b], corners[c]);
+                } else {
+                    skipped = true;
+                }
+            }
+            if skipped {
+                model.skipped_faces.push(face_index);
+            }
+        }
+    }
+
+    Ok(model)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn concave_faces_are_triangulated_inside_their_outline() {
+        // An arrowhead with its notch at the last corner, which a fan from the first corner would cover
+        let source = "ply\nformat ascii 1.0\nelement vertex 4\nproperty float x\nproperty float y\nproperty float z\n\
+            element face 1\nproperty list uchar int vertex_indices\nend_header\n\
+            0 2 0\n2 0 0\n0 -2 0\n1 0 0\n4 0 1 2 3\n";
+        let model = parse_ply(source.as_bytes()).unwrap();
+        assert!(model.skipped_faces.is_empty());
+        let triangles = model.mesh.indices();
+        assert_eq!(triangles.len(), 2);
+        assert!(triangles.iter().all(|&(a, b, c)| [a, b, c].contains(&3)), "{:?}", triangles);
+    }
+
+    #[test]
+    fn binary_elements_without_properties_are_an_error() {
+        let mut data = b"ply\nformat binary_little_endian 1.0\nelement empty 4000000000\nend_header\n".to_vec();
+        data.extend_from_slice(&[0; 16]);
+        assert!(matches!(parse_ply(&data), Err(ImportError::Parse {line: 3, ..})));
+    }
+}
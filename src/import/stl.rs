@@ -0,0 +1,186 @@
+use super::{ByteReader, Endianness, ImportError, checked_triangle, face_normal, is_zero_vector};
+use crate::geometry::{Point3d, Vector3d};
+use crate::mesh::{Mesh, Vertex};
+use crate::render::RGB;
+
+use std::fs;
+use std::path::Path;
+use std::str::SplitWhitespace;
+
+
+const HEADER_SIZE: usize = 80;
+const FACET_SIZE: usize = 50;
+
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StlModel {
+    pub name: String,
+    pub mesh: Mesh,
+    // Indices of facets which were skipped because they are degenerate
+    pub skipped_faces: Vec<usize>,
+}
+
+impl StlModel {
+    fn push_facet(&mut self, index: usize, normal: Vector3d, corners: [Point3d; 3], color: Option<RGB>) {
+        let tri = match checked_triangle(corners[0], corners[1], corners[2]) {
+            Some(tri) => tri,
+            None => {
+                self.skipped_faces.push(index);
+                return;
+            },
+        };
+
+        let normal = if is_zero_vector(normal) { face_normal(&tri) } else { normal };
+        let mut indices = [0; 3];
+        for (index, &corner) in indices.iter_mut().zip(corners.iter()) {
+            let mut vertex = Vertex {normal: Some(normal), ..Vertex::new(corner)};
+            if let Some(color) = color {
+                vertex.color = color;
+            }
+            *index = self.mesh.push_vertex(vertex);
+        }
+        self.mesh.push_triangle(indices[0], indices[1], indices[2]);
+    }
+}
+
+
+pub fn load_stl(path: impl AsRef<Path>) -> Result<StlModel, ImportError> {
+    let path = path.as_ref();
+    let data = fs::read(path)?;
+    parse_stl(&data).map_err(|e| e.in_file(path))
+}
+
+
+pub fn parse_stl(data: &[u8]) -> Result<StlModel, ImportError> {
+    // Binary files may also start with "solid", so the size is checked first
+    if data.len() >= HEADER_SIZE + 4 {
+        let count = u32::from_le_bytes([data[80], data[81], data[82], data[83]]) as usize;
+        if data.len() == HEADER_SIZE + 4 + count * FACET_SIZE {
+            return parse_binary_stl(data);
+        }
+    }
+
+    if data.trim_ascii_start().starts_with(b"solid") {
+        let source = std::str::from_utf8(data).map_err(|_| ImportError::invalid("ASCII STL is not valid UTF-8"))?;
+        parse_ascii_stl(source)
+    } else {
+        parse_binary_stl(data)
+    }
+}
+
+
+pub fn parse_binary_stl(data: &[u8]) -> Result<StlModel, ImportError> {
+    let mut reader = ByteReader::new(data, Endianness::Little);
+    let header: [u8; HEADER_SIZE] = reader.take()?;
+    let count = reader.read_u32()? as usize;
+
+    let name_length = header.iter().position(|&byte| byte == 0).unwrap_or(HEADER_SIZE);
+    let mut model = StlModel {
+        name: String::from_utf8_lossy(&header[..name_length]).trim().to_owned(),
+        ..StlModel::default()
+    };
+
+    for index in 0..count {
+        let normal = read_vector(&mut reader)?;
+        let mut corners = [Point3d {x: 0.0, y: 0.0, z: 0.0}; 3];
+        for corner in corners.iter_mut() {
+            let Vector3d {x, y, z} = read_vector(&mut reader)?;
+            *corner = Point3d {x, y, z};
+        }
+        let attributes = reader.read_u16()?;
+        model.push_facet(index, normal, corners, attribute_color(attributes));
+    }
+    Ok(model)
+}
+
+
+pub fn parse_ascii_stl(source: &str) -> Result<StlModel, ImportError> {
+    let mut model = StlModel::default();
+    let mut facet_index = 0;
+    let mut normal = None;
+    let mut corners = Vec::with_capacity(3);
+
+    for (line_index, line) in source.lines().enumerate() {
+        let line_number = line_index + 1;
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        match keyword {
+            "solid" if model.name.is_empty() => {
+                model.name = line.trim_start()["solid".len()..].trim().to_owned();
+            },
+            "facet" => {
+                if normal.is_some() {
+                    return Err(ImportError::parse(line_number, "Nested `facet`"));
+                }
+                normal = Some(match tokens.next() {
+                    Some("normal") => parse_coordinates(&mut tokens, line_number)?,
+                    _ => Vector3d {x: 0.0, y: 0.0, z: 0.0},
+                });
+            },
+            "vertex" => {
+                if normal.is_none() {
+                    return Err(ImportError::parse(line_number, "`vertex` outside of a facet"));
+                }
+                if corners.len() == 3 {
+                    return Err(ImportError::parse(line_number, "A facet must have exactly three vertices"));
+                }
+                let Vector3d {x, y, z} = parse_coordinates(&mut tokens, line_number)?;
+                corners.push(Point3d {x, y, z});
+            },
+            "endfacet" => {
+                let facet_normal = normal
+                    .take()
+                    .ok_or_else(|| ImportError::parse(line_number, "`endfacet` without `facet`"))?;
+                if corners.len() != 3 {
+                    return Err(ImportError::parse(line_number, "A facet must have exactly three vertices"));
+                }
+                model.push_facet(facet_index, facet_normal, [corners[0], corners[1], corners[2]], None);
+                corners.clear();
+                facet_index += 1;
+            },
+            "outer" | "endloop" | "endsolid" | "solid" => {},
+            _ => return Err(ImportError::parse(line_number, format!("Unexpected keyword `{}`", keyword))),
+        }
+    }
+
+    if normal.is_some() {
+        return Err(ImportError::parse(source.lines().count(), "Unterminated facet"));
+    }
+    Ok(model)
+}
+
+
+fn parse_coordinates(tokens: &mut SplitWhitespace, line_number: usize) -> Result<Vector3d, ImportError> {
+    let mut next = || {
+        let token = tokens.next().ok_or_else(|| ImportError::parse(line_number, "Expected a number"))?;
+        token
+            .parse::<f64>()
+            .map_err(|_| ImportError::parse(line_number, format!("Invalid number `{}`", token)))
+    };
+    Ok(Vector3d {x: next()?, y: next()?, z: next()?})
+}
+
+
+fn read_vector(reader: &mut ByteReader) -> Result<Vector3d, ImportError> {
+    Ok(Vector3d {
+        x: reader.read_f32()? as f64,
+        y: reader.read_f32()? as f64,
+        z: reader.read_f32()? as f64,
+    })
+}
+
+
+// VisCAM/SolidView convention: the top bit marks a valid color, then 5 bits per channel
+fn attribute_color(attributes: u16) -> Option<RGB> {
+    if attributes & 0x8000 == 0 {
+        return None;
+    }
+    let channel = |shift: u16| {
+        let value = (attributes >> shift) & 0x1f;
+        ((value << 3) | (value >> 2)) as u8
+    };
+    Some(RGB::new(channel(10), channel(5), channel(0)))
+}
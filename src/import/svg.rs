@@ -0,0 +1,801 @@
+use super::ImportError;
+use crate::draw2d::{flatten_cubic, flatten_quadratic, Path};
+use crate::geometry::BasicPoint;
+use crate::render::{RGB, FillRule};
+
+use core::f64::consts::{FRAC_PI_2, PI};
+use std::fs;
+use std::path::Path as FilePath;
+
+
+// Only solid fills are supported: strokes, `transform` attributes and CSS stylesheets are ignored, and shapes filled
+// with a gradient, a pattern or another unsupported paint are skipped. Relative lengths (`%`, `em`, ...) count as
+// missing. Everything inside <defs>, <clipPath>, <mask>, <symbol> and similar non-rendered containers is skipped.
+
+
+// Control points of a circle quarter approximated by a cubic Bézier curve
+const KAPPA: f64 = 0.552_284_749_830_793_4;
+
+const HIDDEN_CONTAINERS: [&str; 7] = ["defs", "clipPath", "mask", "symbol", "pattern", "marker", "style"];
+
+
+// Path segments in absolute coordinates; arcs and smooth curves are converted to Bézier curves
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PathSegment {
+    MoveTo(BasicPoint<f64>),
+    LineTo(BasicPoint<f64>),
+    QuadraticTo(BasicPoint<f64>, BasicPoint<f64>),
+    CubicTo(BasicPoint<f64>, BasicPoint<f64>, BasicPoint<f64>),
+    Close,
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SvgShape {
+    pub segments: Vec<PathSegment>,
+    pub fill: RGB,
+    pub fill_rule: FillRule,
+}
+
+impl SvgShape {
+    // Maps user units to pixels as `point * scale + offset`, then flattens curves to within `tolerance` pixels
+    pub fn to_path(&self, offset: BasicPoint<f64>, scale: f64, tolerance: f64) -> Path {
+        let map = |point: BasicPoint<f64>| BasicPoint {x: point.x * scale + offset.x, y: point.y * scale + offset.y};
+        let mut subpaths = Vec::new();
+        let mut subpath: Vec<BasicPoint<f64>> = Vec::new();
+        let mut start = map(BasicPoint {x: 0.0, y: 0.0});
+        let mut current = start;
+        for segment in &self.segments {
+            match *segment {
+                PathSegment::MoveTo(point) => {
+                    if subpath.len() > 1 {
+                        subpaths.push(subpath);
+                    }
+                    start = map(point);
+                    current = start;
+                    subpath = vec![start];
+                    continue;
+                },
+                PathSegment::LineTo(point) => {
+                    current = map(point);
+                    subpath.push(current);
+                },
+                PathSegment::QuadraticTo(control, end) => {
+                    let points = flatten_quadratic(current, map(control), map(end), tolerance);
+                    subpath.extend_from_slice(&points[1..]);
+                    current = map(end);
+                },
+                PathSegment::CubicTo(control1, control2, end) => {
+                    let points = flatten_cubic(current, map(control1), map(control2), map(end), tolerance);
+                    subpath.extend_from_slice(&points[1..]);
+                    current = map(end);
+                },
+                PathSegment::Close => {
+                    if subpath.len() > 1 {
+                        subpaths.push(subpath);
+                    }
+                    // Drawing continues from the start of the closed subpath
+                    current = start;
+                    subpath = vec![start];
+                },
+            }
+        }
+        if subpath.len() > 1 {
+            subpaths.push(subpath);
+        }
+        Path {subpaths, rule: self.fill_rule, color: self.fill}
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SvgDocument {
+    pub width: f64,
+    pub height: f64,
+    pub shapes: Vec<SvgShape>,
+}
+
+impl SvgDocument {
+    pub fn to_paths(&self, offset: BasicPoint<f64>, scale: f64) -> Vec<Path> {
+        self.shapes.iter().map(|shape| shape.to_path(offset, scale, 0.25)).collect()
+    }
+}
+
+
+pub fn load_svg(path: impl AsRef<FilePath>) -> Result<SvgDocument, ImportError> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path)?;
+    parse_svg(&source).map_err(|error| error.in_file(path))
+}
+
+
+pub fn parse_svg(source: &str) -> Result<SvgDocument, ImportError> {
+    let mut document = SvgDocument {width: 0.0, height: 0.0, shapes: Vec::new()};
+    // Maps viewBox coordinates to document units
+    let mut view_scale = 1.0;
+    let mut view_offset = BasicPoint {x: 0.0, y: 0.0};
+    let mut styles: Vec<(String, Style)> = Vec::new();
+    let mut hidden_depth = 0;
+    let mut seen_root = false;
+
+    let mut rest = source;
+    let mut line = 1;
+    while let Some(start) = rest.find('<') {
+        line += rest[..start].matches('\n').count();
+        rest = &rest[start..];
+        let skip_to = |rest: &str, terminator: &str| {
+            rest.find(terminator)
+                .map(|end| end + terminator.len())
+                .ok_or_else(|| ImportError::parse(line, format!("Missing \"{}\"", terminator)))
+        };
+        let end = if rest.starts_with("<!--") {
+            skip_to(rest, "-->")?
+        } else if rest.starts_with("<![CDATA[") {
+            skip_to(rest, "]]>")?
+        } else if rest.starts_with("<?") {
+            skip_to(rest, "?>")?
+        } else if rest.starts_with("<!") {
+            skip_to(rest, ">")?
+        } else {
+            let end = tag_end(rest).ok_or_else(|| ImportError::parse(line, "Unterminated tag"))?;
+            let tag = Tag::parse(&rest[1..end - 1]).map_err(|message| ImportError::parse(line, message))?;
+            handle_tag(&tag, &mut styles, &mut hidden_depth).map_err(|message| ImportError::parse(line, message))?;
+
+            if tag.name == "svg" && !tag.closing && !seen_root {
+                seen_root = true;
+                let (width, height, scale, offset) = root_geometry(&tag).map_err(|m| ImportError::parse(line, m))?;
+                document.width = width;
+                document.height = height;
+                view_scale = scale;
+                view_offset = offset;
+            } else if !tag.closing && hidden_depth == 0 {
+                let style = resolve_style(&tag, &styles).map_err(|message| ImportError::parse(line, message))?;
+                let segments = shape_segments(&tag).map_err(|message| ImportError::parse(line, message))?;
+                if let (Some(segments), Some(fill)) = (segments, style.fill.unwrap_or(Some(RGB::new(0, 0, 0)))) {
+                    let map = |p: BasicPoint<f64>| BasicPoint {
+                        x: p.x * view_scale + view_offset.x,
+                        y: p.y * view_scale + view_offset.y,
+                    };
+                    document.shapes.push(SvgShape {
+                        segments: segments.into_iter().map(|segment| map_segment(segment, map)).collect(),
+                        fill,
+                        fill_rule: style.fill_rule.unwrap_or(FillRule::NonZero),
+                    });
+                }
+            }
+            end
+        };
+        line += rest[..end].matches('\n').count();
+        rest = &rest[end..];
+    }
+
+    if !seen_root {
+        return Err(ImportError::invalid("No <svg> element found"));
+    }
+    Ok(document)
+}
+
+
+pub fn parse_path_data(data: &str) -> Result<Vec<PathSegment>, ImportError> {
+    path_data(data).map_err(ImportError::invalid)
+}
+
+
+// Fill properties, `None` meaning inherited; a fill of `Some(None)` is "none"
+#[derive(Debug, Clone, Copy, Default)]
+struct Style {
+    fill: Option<Option<RGB>>,
+    fill_rule: Option<FillRule>,
+}
+
+
+struct Tag<'a> {
+    name: &'a str,
+    closing: bool,
+    self_closing: bool,
+    attributes: Vec<(&'a str, String)>,
+}
+
+impl Tag<'_> {
+    // Parses the text between `<` and `>`
+    fn parse(text: &str) -> Result<Tag<'_>, String> {
+        let closing = text.starts_with('/');
+        let self_closing = text.ends_with('/');
+        let text = text.trim_start_matches('/').trim_end_matches('/');
+        let name_end = text.find(|c: char| c.is_whitespace()).unwrap_or(text.len());
+        let name = &text[..name_end];
+        if name.is_empty() {
+            return Err("Missing tag name".to_owned());
+        }
+
+        let mut attributes = Vec::new();
+        let mut rest = text[name_end..].trim_start();
+        while !rest.is_empty() {
+            let equals = rest.find('=').ok_or_else(|| format!("Attribute without a value in <{}>", name))?;
+            let key = rest[..equals].trim();
+            let after = rest[equals + 1..].trim_start();
+            let quote = after.chars().next().filter(|&c| c == '"' || c == '\'');
+            let quote = quote.ok_or_else(|| format!("Unquoted value of attribute \"{}\"", key))?;
+            let value_end = after[1..].find(quote).ok_or_else(|| format!("Unterminated value of \"{}\"", key))?;
+            attributes.push((key, decode_entities(&after[1..1 + value_end])));
+            rest = after[value_end + 2..].trim_start();
+        }
+        Ok(Tag {name, closing, self_closing, attributes})
+    }
+
+    fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes.iter().find(|(name, _)| *name == key).map(|(_, value)| value.as_str())
+    }
+
+    fn length(&self, key: &str) -> Result<Option<f64>, String> {
+        Ok(self.attribute(key).map(parse_length).transpose()?.flatten())
+    }
+}
+
+
+// The index just past the `>` closing the tag, skipping `>` inside quoted attribute values
+fn tag_end(text: &str) -> Option<usize> {
+    let mut quote = None;
+    for (index, c) in text.char_indices() {
+        match (quote, c) {
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (Some(open), _) if c == open => quote = None,
+            (None, '>') => return Some(index + 1),
+            _ => {},
+        }
+    }
+    None
+}
+
+
+fn decode_entities(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+
+// Keeps the stack of inherited styles and the nesting of non-rendered containers up to date
+fn handle_tag(tag: &Tag<'_>, styles: &mut Vec<(String, Style)>, hidden_depth: &mut usize) -> Result<(), String> {
+    if tag.self_closing {
+        return Ok(());
+    }
+    let hidden = HIDDEN_CONTAINERS.contains(&tag.name);
+    if tag.closing {
+        if hidden {
+            *hidden_depth = hidden_depth.saturating_sub(1);
+        }
+        if styles.last().is_some_and(|(name, _)| name == tag.name) {
+            styles.pop();
+        }
+    } else if hidden {
+        *hidden_depth += 1;
+    } else if tag.name == "g" || tag.name == "svg" || tag.name == "a" {
+        let style = own_style(tag)?;
+        styles.push((tag.name.to_owned(), style));
+    }
+    Ok(())
+}
+
+
+fn own_style(tag: &Tag<'_>) -> Result<Style, String> {
+    let mut style = Style::default();
+    let mut apply = |property: &str, value: &str| -> Result<(), String> {
+        match property {
+            "fill" => style.fill = parse_paint(value),
+            "fill-rule" => style.fill_rule = Some(parse_fill_rule(value)?),
+            _ => {},
+        }
+        Ok(())
+    };
+    for (key, value) in &tag.attributes {
+        apply(key, value)?;
+    }
+    // Declarations in `style` take precedence over presentation attributes
+    for declaration in tag.attribute("style").unwrap_or("").split(';') {
+        if let Some((property, value)) = declaration.split_once(':') {
+            apply(property.trim(), value.trim())?;
+        }
+    }
+    Ok(style)
+}
+
+
+fn resolve_style(tag: &Tag<'_>, styles: &[(String, Style)]) -> Result<Style, String> {
+    let mut style = own_style(tag)?;
+    for (_, inherited) in styles.iter().rev() {
+        style.fill = style.fill.or(inherited.fill);
+        style.fill_rule = style.fill_rule.or(inherited.fill_rule);
+    }
+    Ok(style)
+}
+
+
+// Document size and the mapping of viewBox coordinates into it, centred with a uniform scale
+fn root_geometry(tag: &Tag<'_>) -> Result<(f64, f64, f64, BasicPoint<f64>), String> {
+    let view_box = match tag.attribute("viewBox") {
+        Some(view_box) => {
+            let numbers = view_box
+                .split(|c: char| c.is_whitespace() || c == ',')
+                .filter(|part| !part.is_empty())
+                .map(|part| part.parse::<f64>().map_err(|_| format!("Invalid viewBox \"{}\"", view_box)))
+                .collect::<Result<Vec<_>, _>>()?;
+            match numbers[..] {
+                [x, y, width, height] if width > 0.0 && height > 0.0 => Some((x, y, width, height)),
+                _ => return Err(format!("Invalid viewBox \"{}\"", view_box)),
+            }
+        },
+        None => None,
+    };
+
+    let width = tag.length("width")?.or(view_box.map(|view_box| view_box.2)).unwrap_or(0.0);
+    let height = tag.length("height")?.or(view_box.map(|view_box| view_box.3)).unwrap_or(0.0);
+    Ok(match view_box {
+        Some((x, y, view_width, view_height)) => {
+            let scale = (width / view_width).min(height / view_height);
+            let offset = BasicPoint {
+                x: (width - view_width * scale) / 2.0 - x * scale,
+                y: (height - view_height * scale) / 2.0 - y * scale,
+            };
+            (width, height, scale, offset)
+        },
+        None => (width, height, 1.0, BasicPoint {x: 0.0, y: 0.0}),
+    })
+}
+
+
+// Outlines of supported shape elements; `None` for other elements and for shapes that are not rendered
+fn shape_segments(tag: &Tag<'_>) -> Result<Option<Vec<PathSegment>>, String> {
+    let length = |key: &str| tag.length(key).map(|value| value.unwrap_or(0.0));
+    let point = |x: f64, y: f64| BasicPoint {x, y};
+    Ok(match tag.name {
+        "path" => Some(path_data(tag.attribute("d").unwrap_or(""))?),
+        "rect" => {
+            let (x, y, width, height) = (length("x")?, length("y")?, length("width")?, length("height")?);
+            if width <= 0.0 || height <= 0.0 {
+                return Ok(None);
+            }
+            // A missing corner radius defaults to the other one
+            let (rx, ry) = match (tag.length("rx")?, tag.length("ry")?) {
+                (Some(rx), Some(ry)) => (rx, ry),
+                (Some(r), None) | (None, Some(r)) => (r, r),
+                (None, None) => (0.0, 0.0),
+            };
+            Some(rounded_rect(x, y, width, height, rx.clamp(0.0, width / 2.0), ry.clamp(0.0, height / 2.0)))
+        },
+        "circle" => {
+            let radius = length("r")?;
+            if radius <= 0.0 {
+                return Ok(None);
+            }
+            Some(ellipse(point(length("cx")?, length("cy")?), radius, radius))
+        },
+        "ellipse" => {
+            let (rx, ry) = (length("rx")?, length("ry")?);
+            if rx <= 0.0 || ry <= 0.0 {
+                return Ok(None);
+            }
+            Some(ellipse(point(length("cx")?, length("cy")?), rx, ry))
+        },
+        // Polylines are filled as if they were closed
+        "polygon" | "polyline" => {
+            let mut lexer = PathLexer::new(tag.attribute("points").unwrap_or(""));
+            let mut segments = Vec::new();
+            while lexer.has_number() {
+                let vertex = lexer.point()?;
+                segments.push(match segments.is_empty() {
+                    true => PathSegment::MoveTo(vertex),
+                    false => PathSegment::LineTo(vertex),
+                });
+            }
+            segments.push(PathSegment::Close);
+            Some(segments)
+        },
+        _ => None,
+    })
+}
+
+
+fn rounded_rect(x: f64, y: f64, width: f64, height: f64, rx: f64, ry: f64) -> Vec<PathSegment> {
+    let point = |x: f64, y: f64| BasicPoint {x, y};
+    let (right, bottom) = (x + width, y + height);
+    let (kx, ky) = (rx * KAPPA, ry * KAPPA);
+    let mut segments = vec![PathSegment::MoveTo(point(x + rx, y)), PathSegment::LineTo(point(right - rx, y))];
+    let corner = |segments: &mut Vec<PathSegment>, control1, control2, end| {
+        if rx > 0.0 && ry > 0.0 {
+            segments.push(PathSegment::CubicTo(control1, control2, end));
+        }
+    };
+    corner(&mut segments, point(right - rx + kx, y), point(right, y + ry - ky), point(right, y + ry));
+    segments.push(PathSegment::LineTo(point(right, bottom - ry)));
+    corner(&mut segments, point(right, bottom - ry + ky), point(right - rx + kx, bottom), point(right - rx, bottom));
+    segments.push(PathSegment::LineTo(point(x + rx, bottom)));
+    corner(&mut segments, point(x + rx - kx, bottom), point(x, bottom - ry + ky), point(x, bottom - ry));
+    segments.push(PathSegment::LineTo(point(x, y + ry)));
+    corner(&mut segments, point(x, y + ry - ky), point(x + rx - kx, y), point(x + rx, y));
+    segments.push(PathSegment::Close);
+    segments
+}
+
+
+fn ellipse(center: BasicPoint<f64>, rx: f64, ry: f64) -> Vec<PathSegment> {
+    let point = |x: f64, y: f64| BasicPoint {x: center.x + x, y: center.y + y};
+    let (kx, ky) = (rx * KAPPA, ry * KAPPA);
+    vec![
+        PathSegment::MoveTo(point(rx, 0.0)),
+        PathSegment::CubicTo(point(rx, ky), point(kx, ry), point(0.0, ry)),
+        PathSegment::CubicTo(point(-kx, ry), point(-rx, ky), point(-rx, 0.0)),
+        PathSegment::CubicTo(point(-rx, -ky), point(-kx, -ry), point(0.0, -ry)),
+        PathSegment::CubicTo(point(kx, -ry), point(rx, -ky), point(rx, 0.0)),
+        PathSegment::Close,
+    ]
+}
+
+
+fn map_segment(segment: PathSegment, map: impl Fn(BasicPoint<f64>) -> BasicPoint<f64>) -> PathSegment {
+    match segment {
+        PathSegment::MoveTo(point) => PathSegment::MoveTo(map(point)),
+        PathSegment::LineTo(point) => PathSegment::LineTo(map(point)),
+        PathSegment::QuadraticTo(control, end) => PathSegment::QuadraticTo(map(control), map(end)),
+        PathSegment::CubicTo(control1, control2, end) => PathSegment::CubicTo(map(control1), map(control2), map(end)),
+        PathSegment::Close => PathSegment::Close,
+    }
+}
+
+
+// Converts absolute units to user units, which are pixels; `None` for relative units, which are not resolved
+fn parse_length(value: &str) -> Result<Option<f64>, String> {
+    let value = value.trim();
+    let number_end = value.trim_end_matches(|c: char| c.is_ascii_alphabetic() || c == '%').len();
+    let number: f64 = value[..number_end].trim().parse().map_err(|_| format!("Invalid length \"{}\"", value))?;
+    let unit_size = match &value[number_end..] {
+        "" | "px" => 1.0,
+        "pt" => 4.0 / 3.0,
+        "pc" => 16.0,
+        "mm" => 96.0 / 25.4,
+        "cm" => 96.0 / 2.54,
+        "in" => 96.0,
+        _ => return Ok(None),
+    };
+    Ok(Some(number * unit_size))
+}
+
+
+fn parse_fill_rule(value: &str) -> Result<FillRule, String> {
+    match value.trim() {
+        "nonzero" => Ok(FillRule::NonZero),
+        "evenodd" => Ok(FillRule::EvenOdd),
+        other => Err(format!("Unknown fill rule \"{}\"", other)),
+    }
+}
+
+
+// `None` for an inherited paint and `Some(None)` for no fill, which unsupported paints fall back to
+fn parse_paint(value: &str) -> Option<Option<RGB>> {
+    let value = value.trim();
+    if value == "inherit" {
+        return None;
+    }
+    // Paint servers such as gradients may name a fallback color after the reference
+    if let Some(reference) = value.strip_prefix("url(") {
+        let fallback = reference.split_once(')').map_or("", |(_, fallback)| fallback.trim());
+        return Some(parse_color(fallback).ok().flatten());
+    }
+    Some(parse_color(value).ok().flatten())
+}
+
+
+// `None` stands for no fill
+fn parse_color(value: &str) -> Result<Option<RGB>, String> {
+    let value = value.trim();
+    let invalid = || format!("Unsupported color \"{}\"", value);
+    if let Some(hex) = value.strip_prefix('#') {
+        let digits = hex.chars().map(|c| c.to_digit(16).map(|digit| digit as u8)).collect::<Option<Vec<_>>>();
+        return match digits.as_deref() {
+            Some(&[r, g, b]) => Ok(Some(RGB::new(r * 17, g * 17, b * 17))),
+            Some(&[r1, r2, g1, g2, b1, b2]) => Ok(Some(RGB::new(r1 * 16 + r2, g1 * 16 + g2, b1 * 16 + b2))),
+            _ => Err(invalid()),
+        };
+    }
+    if let Some(arguments) = value.strip_prefix("rgb(").and_then(|rest| rest.strip_suffix(')')) {
+        let channels = arguments
+            .split(',')
+            .map(|channel| {
+                let channel = channel.trim();
+                match channel.strip_suffix('%') {
+                    Some(percent) => percent.trim().parse::<f64>().map(|percent| percent * 2.55),
+                    None => channel.parse::<f64>(),
+                }
+                .map(|channel| channel.round().clamp(0.0, 255.0) as u8)
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| invalid())?;
+        return match channels[..] {
+            [r, g, b] => Ok(Some(RGB::new(r, g, b))),
+            _ => Err(invalid()),
+        };
+    }
+    let (r, g, b) = match value {
+        "none" | "transparent" => return Ok(None),
+        "black" => (0, 0, 0),
+        "white" => (255, 255, 255),
+        "red" => (255, 0, 0),
+        "lime" => (0, 255, 0),
+        "green" => (0, 128, 0),
+        "blue" => (0, 0, 255),
+        "yellow" => (255, 255, 0),
+        "cyan" | "aqua" => (0, 255, 255),
+        "magenta" | "fuchsia" => (255, 0, 255),
+        "gray" | "grey" => (128, 128, 128),
+        "silver" => (192, 192, 192),
+        "maroon" => (128, 0, 0),
+        "olive" => (128, 128, 0),
+        "navy" => (0, 0, 128),
+        "purple" => (128, 0, 128),
+        "teal" => (0, 128, 128),
+        "orange" => (255, 165, 0),
+        _ => return Err(invalid()),
+    };
+    Ok(Some(RGB::new(r, g, b)))
+}
+
+
+fn path_data(data: &str) -> Result<Vec<PathSegment>, String> {
+    let mut lexer = PathLexer::new(data);
+    let mut segments = Vec::new();
+    let origin = BasicPoint {x: 0.0, y: 0.0};
+    let (mut current, mut start) = (origin, origin);
+    // The reflected control point of the previous curve, for the smooth S and T commands
+    let mut last_cubic_control: Option<BasicPoint<f64>> = None;
+    let mut last_quadratic_control: Option<BasicPoint<f64>> = None;
+    let mut previous_command: Option<char> = None;
+
+    while let Some(command) = lexer.command(previous_command)? {
+        let relative = command.is_ascii_lowercase();
+        let absolute = |point: BasicPoint<f64>| {
+            if relative { BasicPoint {x: current.x + point.x, y: current.y + point.y} } else { point }
+        };
+        let reflect = |control: Option<BasicPoint<f64>>| match control {
+            Some(control) => BasicPoint {x: 2.0 * current.x - control.x, y: 2.0 * current.y - control.y},
+            None => current,
+        };
+        let (mut cubic_control, mut quadratic_control) = (None, None);
+
+        match command.to_ascii_uppercase() {
+            'M' => {
+                current = absolute(lexer.point()?);
+                start = current;
+                segments.push(PathSegment::MoveTo(current));
+            },
+            'L' => {
+                current = absolute(lexer.point()?);
+                segments.push(PathSegment::LineTo(current));
+            },
+            'H' => {
+                let x = lexer.number()?;
+                current = BasicPoint {x: if relative { current.x + x } else { x }, y: current.y};
+                segments.push(PathSegment::LineTo(current));
+            },
+            'V' => {
+                let y = lexer.number()?;
+                current = BasicPoint {x: current.x, y: if relative { current.y + y } else { y }};
+                segments.push(PathSegment::LineTo(current));
+            },
+            'C' | 'S' => {
+                let control1 = if command.eq_ignore_ascii_case(&'S') {
+                    reflect(last_cubic_control)
+                } else {
+                    absolute(lexer.point()?)
+                };
+                let control2 = absolute(lexer.point()?);
+                let end = absolute(lexer.point()?);
+                segments.push(PathSegment::CubicTo(control1, control2, end));
+                cubic_control = Some(control2);
+                current = end;
+            },
+            'Q' | 'T' => {
+                let control = if command.eq_ignore_ascii_case(&'T') {
+                    reflect(last_quadratic_control)
+                } else {
+                    absolute(lexer.point()?)
+                };
+                let end = absolute(lexer.point()?);
+                segments.push(PathSegment::QuadraticTo(control, end));
+                quadratic_control = Some(control);
+                current = end;
+            },
+            'A' => {
+                let (rx, ry, rotation) = (lexer.number()?, lexer.number()?, lexer.number()?);
+                let (large_arc, sweep) = (lexer.flag()?, lexer.flag()?);
+                let end = absolute(lexer.point()?);
+                segments.extend(arc_segments(current, rx, ry, rotation, large_arc, sweep, end));
+                current = end;
+            },
+            'Z' => {
+                segments.push(PathSegment::Close);
+                current = start;
+            },
+            other => return Err(format!("Unknown path command '{}'", other)),
+        }
+
+        last_cubic_control = cubic_control;
+        last_quadratic_control = quadratic_control;
+        // Coordinates following a move are implicit line commands
+        previous_command = Some(match command {
+            'M' => 'L',
+            'm' => 'l',
+            _ => command,
+        });
+    }
+    if let Some(PathSegment::MoveTo(_)) | None = segments.first() {
+        Ok(segments)
+    } else {
+        Err("Path data must start with a move command".to_owned())
+    }
+}
+
+
+// Converts an elliptical arc from endpoint to centre parameterisation (SVG 1.1, appendix F.6.5)
+// and approximates it with one cubic Bézier curve per quarter turn at most
+#[allow(clippy::too_many_arguments)]
+fn arc_segments(
+    from: BasicPoint<f64>,
+    rx: f64,
+    ry: f64,
+    rotation: f64,
+    large_arc: bool,
+    sweep: bool,
+    to: BasicPoint<f64>
+) -> Vec<PathSegment> {
+    let (mut rx, mut ry) = (rx.abs(), ry.abs());
+    if from == to {
+        return Vec::new();
+    }
+    if rx == 0.0 || ry == 0.0 {
+        return vec![PathSegment::LineTo(to)];
+    }
+
+    let (sin, cos) = rotation.to_radians().sin_cos();
+    let (half_dx, half_dy) = ((from.x - to.x) / 2.0, (from.y - to.y) / 2.0);
+    let x1 = cos * half_dx + sin * half_dy;
+    let y1 = -sin * half_dx + cos * half_dy;
+
+    // Radii too small to reach the end point are scaled up
+    let lambda = (x1 / rx).powi(2) + (y1 / ry).powi(2);
+    if lambda > 1.0 {
+        rx *= lambda.sqrt();
+        ry *= lambda.sqrt();
+    }
+    let numerator = (rx * ry).powi(2) - (rx * y1).powi(2) - (ry * x1).powi(2);
+    let denominator = (rx * y1).powi(2) + (ry * x1).powi(2);
+    let sign = if large_arc == sweep { -1.0 } else { 1.0 };
+    let coefficient = sign * (numerator / denominator).max(0.0).sqrt();
+    let (center_x1, center_y1) = (coefficient * rx * y1 / ry, -coefficient * ry * x1 / rx);
+    let center = BasicPoint {
+        x: cos * center_x1 - sin * center_y1 + (from.x + to.x) / 2.0,
+        y: sin * center_x1 + cos * center_y1 + (from.y + to.y) / 2.0,
+    };
+
+    let (ux, uy) = ((x1 - center_x1) / rx, (y1 - center_y1) / ry);
+    let (vx, vy) = ((-x1 - center_x1) / rx, (-y1 - center_y1) / ry);
+    let start_angle = uy.atan2(ux);
+    let mut sweep_angle = (ux * vy - uy * vx).atan2(ux * vx + uy * vy);
+    if !sweep && sweep_angle > 0.0 {
+        sweep_angle -= 2.0 * PI;
+    } else if sweep && sweep_angle < 0.0 {
+        sweep_angle += 2.0 * PI;
+    }
+
+    let map = |x: f64, y: f64| BasicPoint {
+        x: center.x + rx * x * cos - ry * y * sin,
+        y: center.y + rx * x * sin + ry * y * cos,
+    };
+    let count = (sweep_angle.abs() / FRAC_PI_2).ceil().max(1.0) as usize;
+    let step = sweep_angle / count as f64;
+    let handle = 4.0 / 3.0 * (step / 4.0).tan();
+    (0..count)
+        .map(|index| {
+            let (angle1, angle2) = (start_angle + step * index as f64, start_angle + step * (index + 1) as f64);
+            let (sin1, cos1) = angle1.sin_cos();
+            let (sin2, cos2) = angle2.sin_cos();
+            // The last end point is exact, so that following segments do not drift
+            let end = if index + 1 == count { to } else { map(cos2, sin2) };
+            PathSegment::CubicTo(
+                map(cos1 - handle * sin1, sin1 + handle * cos1),
+                map(cos2 + handle * sin2, sin2 - handle * cos2),
+                end,
+            )
+        })
+        .collect()
+}
+
+
+struct PathLexer<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl PathLexer<'_> {
+    fn new(data: &str) -> PathLexer<'_> {
+        PathLexer {bytes: data.as_bytes(), position: 0}
+    }
+
+    fn skip_separators(&mut self) {
+        while self.bytes.get(self.position).is_some_and(|&byte| byte.is_ascii_whitespace() || byte == b',') {
+            self.position += 1;
+        }
+    }
+
+    fn has_number(&mut self) -> bool {
+        self.skip_separators();
+        matches!(self.bytes.get(self.position), Some(b'0'..=b'9') | Some(b'-') | Some(b'+') | Some(b'.'))
+    }
+
+    // The next command letter, or the previous command repeated when more numbers follow
+    fn command(&mut self, previous: Option<char>) -> Result<Option<char>, String> {
+        self.skip_separators();
+        match self.bytes.get(self.position) {
+            None => Ok(None),
+            Some(byte) if byte.is_ascii_alphabetic() => {
+                self.position += 1;
+                Ok(Some(*byte as char))
+            },
+            Some(_) => match previous {
+                Some(previous) if !previous.eq_ignore_ascii_case(&'Z') && self.has_number() => Ok(Some(previous)),
+                _ => Err(format!("Expected a path command at offset {}", self.position)),
+            },
+        }
+    }
+
+    fn number(&mut self) -> Result<f64, String> {
+        self.skip_separators();
+        let start = self.position;
+        let digits = |lexer: &mut PathLexer<'_>| {
+            while lexer.bytes.get(lexer.position).is_some_and(u8::is_ascii_digit) {
+                lexer.position += 1;
+            }
+        };
+        if matches!(self.bytes.get(self.position), Some(b'-') | Some(b'+')) {
+            self.position += 1;
+        }
+        digits(self);
+        // A second dot starts the next number, as in "0.5.5"
+        if self.bytes.get(self.position) == Some(&b'.') {
+            self.position += 1;
+            digits(self);
+        }
+        if matches!(self.bytes.get(self.position), Some(b'e') | Some(b'E')) {
+            let exponent_start = self.position;
+            self.position += 1;
+            if matches!(self.bytes.get(self.position), Some(b'-') | Some(b'+')) {
+                self.position += 1;
+            }
+            if self.bytes.get(self.position).is_some_and(u8::is_ascii_digit) {
+                digits(self);
+            } else {
+                self.position = exponent_start;
+            }
+        }
+        let text = std::str::from_utf8(&self.bytes[start..self.position]).unwrap_or("");
+        text.parse().map_err(|_| format!("Expected a number at offset {}", start))
+    }
+
+    // Arc flags may be written without separators, as in "a1 1 0 00 1 1"
+    fn flag(&mut self) -> Result<bool, String> {
+        self.skip_separators();
+        let flag = match self.bytes.get(self.position) {
+            Some(b'0') => false,
+            Some(b'1') => true,
+            _ => return Err(format!("Expected an arc flag at offset {}", self.position)),
+        };
+        self.position += 1;
+        Ok(flag)
+    }
+
+    fn point(&mut self) -> Result<BasicPoint<f64>, String> {
+        Ok(BasicPoint {x: self.number()?, y: self.number()?})
+    }
+}
@@ -1,3 +1,5 @@
+use crate::geometry::{Angle, Dot, Norm, Normalize, Par3d, Point3d, Triangle3d, Vector3d};
+
 use std::ops::{Add, Sub, Mul, Div, Neg};
 
 
@@ -106,3 +108,402 @@ impl<
         self.inverse_c_matrix * canonical_coords
     }
 }
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Matrix3d {
+    pub rows: [[f64; 3]; 3],
+}
+
+impl Matrix3d {
+    pub fn identity() -> Matrix3d {
+        Matrix3d::diagonal(Vector3d {x: 1.0, y: 1.0, z: 1.0})
+    }
+
+    pub fn diagonal(diagonal: Vector3d) -> Matrix3d {
+        Matrix3d {rows: [[diagonal.x, 0.0, 0.0], [0.0, diagonal.y, 0.0], [0.0, 0.0, diagonal.z]]}
+    }
+
+    pub fn from_rows(rows: [[f64; 3]; 3]) -> Matrix3d {
+        Matrix3d {rows}
+    }
+
+    pub fn from_columns(col0: Vector3d, col1: Vector3d, col2: Vector3d) -> Matrix3d {
+        Matrix3d {rows: [[col0.x, col1.x, col2.x], [col0.y, col1.y, col2.y], [col0.z, col1.z, col2.z]]}
+    }
+
+    pub fn column(&self, index: usize) -> Vector3d {
+        Vector3d {x: self.rows[0][index], y: self.rows[1][index], z: self.rows[2][index]}
+    }
+
+    pub fn transpose(&self) -> Matrix3d {
+        Matrix3d::from_columns(self.row(0), self.row(1), self.row(2))
+    }
+
+    pub fn det(&self) -> f64 {
+        let m = &self.rows;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+
+    pub fn inverse(&self) -> Option<Matrix3d> {
+        let det = self.det();
+        if det == 0.0 || !det.is_finite() {
+            return None;
+        }
+        // The adjugate is the transposed cofactor matrix; cyclic indices take care of the signs
+        let m = &self.rows;
+        let mut rows = [[0.0; 3]; 3];
+        for (row, result_row) in rows.iter_mut().enumerate() {
+            for (column, cell) in result_row.iter_mut().enumerate() {
+                let (r1, r2) = ((column + 1) % 3, (column + 2) % 3);
+                let (c1, c2) = ((row + 1) % 3, (row + 2) % 3);
+                *cell = (m[r1][c1] * m[r2][c2] - m[r1][c2] * m[r2][c1]) / det;
+            }
+        }
+        Some(Matrix3d {rows})
+    }
+
+    fn row(&self, index: usize) -> Vector3d {
+        let row = self.rows[index];
+        Vector3d {x: row[0], y: row[1], z: row[2]}
+    }
+}
+
+impl Mul for Matrix3d {
+    type Output = Matrix3d;
+
+    fn mul(self, other: Matrix3d) -> Matrix3d {
+        let mut rows = [[0.0; 3]; 3];
+        for (row, result_row) in rows.iter_mut().enumerate() {
+            for (column, cell) in result_row.iter_mut().enumerate() {
+                *cell = (0..3).map(|k| self.rows[row][k] * other.rows[k][column]).sum();
+            }
+        }
+        Matrix3d {rows}
+    }
+}
+
+impl Mul<Vector3d> for Matrix3d {
+    type Output = Vector3d;
+
+    fn mul(self, vector: Vector3d) -> Vector3d {
+        Vector3d {x: self.row(0).dot(&vector), y: self.row(1).dot(&vector), z: self.row(2).dot(&vector)}
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quaternion {
+    pub w: f64,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl Quaternion {
+    pub fn identity() -> Quaternion {
+        Quaternion {w: 1.0, x: 0.0, y: 0.0, z: 0.0}
+    }
+
+    // Rotation by `angle` around `axis` following the right-hand rule
+    pub fn from_axis_angle(axis: Vector3d, angle: Angle) -> Quaternion {
+        let norm = axis.norm();
+        assert!(norm > 0.0, "Rotation axis must not be a zero vector");
+        let (sin, cos) = (angle.as_radians() / 2.0).sin_cos();
+        let scale = sin / norm;
+        Quaternion {w: cos, x: axis.x * scale, y: axis.y * scale, z: axis.z * scale}
+    }
+
+    pub fn norm(&self) -> f64 {
+        self.dot(self).sqrt()
+    }
+
+    pub fn dot(&self, other: &Quaternion) -> f64 {
+        self.w * other.w + self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn normalized(self) -> Quaternion {
+        self.scaled(self.norm().recip())
+    }
+
+    pub fn conjugate(self) -> Quaternion {
+        Quaternion {w: self.w, x: -self.x, y: -self.y, z: -self.z}
+    }
+
+    pub fn inverse(self) -> Option<Quaternion> {
+        let norm_squared = self.dot(&self);
+        if norm_squared == 0.0 {
+            None
+        } else {
+            Some(self.conjugate().scaled(norm_squared.recip()))
+        }
+    }
+
+    // Spherical linear interpolation along the shortest arc
+    pub fn slerp(self, other: Quaternion, t: f64) -> Quaternion {
+        let (from, mut to) = (self.normalized(), other.normalized());
+        let mut cos = from.dot(&to);
+        if cos < 0.0 {
+            to = to.scaled(-1.0);
+            cos = -cos;
+        }
+        if cos > 1.0 - 1e-9 {
+            // The quaternions are almost equal, a linear interpolation is precise enough and avoids dividing by zero
+            return (from.scaled(1.0 - t) + to.scaled(t)).normalized();
+        }
+        let angle = cos.acos();
+        let sin = angle.sin();
+        from.scaled(((1.0 - t) * angle).sin() / sin) + to.scaled((t * angle).sin() / sin)
+    }
+
+    pub fn to_matrix(self) -> Matrix3d {
+        let Quaternion {w, x, y, z} = self.normalized();
+        Matrix3d::from_rows([
+            [1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y - z * w), 2.0 * (x * z + y * w)],
+            [2.0 * (x * y + z * w), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z - x * w)],
+            [2.0 * (x * z - y * w), 2.0 * (y * z + x * w), 1.0 - 2.0 * (x * x + y * y)],
+        ])
+    }
+
+    pub fn rotate(self, vector: Vector3d) -> Vector3d {
+        self.to_matrix() * vector
+    }
+
+    fn scaled(self, factor: f64) -> Quaternion {
+        Quaternion {w: self.w * factor, x: self.x * factor, y: self.y * factor, z: self.z * factor}
+    }
+}
+
+impl Add for Quaternion {
+    type Output = Quaternion;
+
+    fn add(self, other: Quaternion) -> Quaternion {
+        Quaternion {w: self.w + other.w, x: self.x + other.x, y: self.y + other.y, z: self.z + other.z}
+    }
+}
+
+// Hamilton product: `a * b` rotates by `b` first and by `a` second
+impl Mul for Quaternion {
+    type Output = Quaternion;
+
+    fn mul(self, other: Quaternion) -> Quaternion {
+        Quaternion {
+            w: self.w * other.w - self.x * other.x - self.y * other.y - self.z * other.z,
+            x: self.w * other.x + self.x * other.w + self.y * other.z - self.z * other.y,
+            y: self.w * other.y - self.x * other.z + self.y * other.w + self.z * other.x,
+            z: self.w * other.z + self.x * other.y - self.y * other.x + self.z * other.w,
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Matrix4d {
+    pub rows: [[f64; 4]; 4],
+}
+
+impl Matrix4d {
+    pub fn identity() -> Matrix4d {
+        let mut rows = [[0.0; 4]; 4];
+        for (index, row) in rows.iter_mut().enumerate() {
+            row[index] = 1.0;
+        }
+        Matrix4d {rows}
+    }
+
+    pub fn from_rows(rows: [[f64; 4]; 4]) -> Matrix4d {
+        Matrix4d {rows}
+    }
+
+    // glTF and OpenGL store matrices column by column
+    pub fn from_column_major(values: &[f64; 16]) -> Matrix4d {
+        let mut rows = [[0.0; 4]; 4];
+        for (index, value) in values.iter().enumerate() {
+            rows[index % 4][index / 4] = *value;
+        }
+        Matrix4d {rows}
+    }
+
+    pub fn transpose(&self) -> Matrix4d {
+        let mut rows = [[0.0; 4]; 4];
+        for (row, result_row) in rows.iter_mut().enumerate() {
+            for (column, cell) in result_row.iter_mut().enumerate() {
+                *cell = self.rows[column][row];
+            }
+        }
+        Matrix4d {rows}
+    }
+
+    // Gauss-Jordan elimination with partial pivoting
+    pub fn inverse(&self) -> Option<Matrix4d> {
+        let mut left = self.rows;
+        let mut right = Matrix4d::identity().rows;
+        for column in 0..4 {
+            let pivot = (column..4).max_by(|&a, &b| left[a][column].abs().total_cmp(&left[b][column].abs()))?;
+            if left[pivot][column] == 0.0 || !left[pivot][column].is_finite() {
+                return None;
+            }
+            left.swap(column, pivot);
+            right.swap(column, pivot);
+
+            let factor = left[column][column].recip();
+            for k in 0..4 {
+                left[column][k] *= factor;
+                right[column][k] *= factor;
+            }
+            for row in (0..4).filter(|&row| row != column) {
+                let factor = left[row][column];
+                for k in 0..4 {
+                    left[row][k] -= factor * left[column][k];
+                    right[row][k] -= factor * right[column][k];
+                }
+            }
+        }
+        Some(Matrix4d {rows: right})
+    }
+
+    // Applies the matrix to a point in homogeneous coordinates, dividing by `w`
+    pub fn transform_point(&self, point: Point3d) -> Point3d {
+        let m = &self.rows;
+        let apply = |row: usize| m[row][0] * point.x + m[row][1] * point.y + m[row][2] * point.z + m[row][3];
+        let w = apply(3);
+        Point3d {x: apply(0) / w, y: apply(1) / w, z: apply(2) / w}
+    }
+}
+
+impl Mul for Matrix4d {
+    type Output = Matrix4d;
+
+    fn mul(self, other: Matrix4d) -> Matrix4d {
+        let mut rows = [[0.0; 4]; 4];
+        for (row, result_row) in rows.iter_mut().enumerate() {
+            for (column, cell) in result_row.iter_mut().enumerate() {
+                *cell = (0..4).map(|k| self.rows[row][k] * other.rows[k][column]).sum();
+            }
+        }
+        Matrix4d {rows}
+    }
+}
+
+
+// An affine transform: a linear map followed by a translation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub linear: Matrix3d,
+    pub translation: Vector3d,
+}
+
+impl Transform {
+    pub fn identity() -> Transform {
+        Transform {linear: Matrix3d::identity(), translation: Vector3d {x: 0.0, y: 0.0, z: 0.0}}
+    }
+
+    pub fn from_translation(translation: Vector3d) -> Transform {
+        Transform {translation, ..Transform::identity()}
+    }
+
+    pub fn from_rotation(rotation: Quaternion) -> Transform {
+        Transform {linear: rotation.to_matrix(), ..Transform::identity()}
+    }
+
+    pub fn from_scale(scale: Vector3d) -> Transform {
+        Transform {linear: Matrix3d::diagonal(scale), ..Transform::identity()}
+    }
+
+    // Scales first, then rotates, then translates
+    pub fn from_trs(translation: Vector3d, rotation: Quaternion, scale: Vector3d) -> Transform {
+        Transform {linear: rotation.to_matrix() * Matrix3d::diagonal(scale), translation}
+    }
+
+    // Fails for projective matrices, whose bottom row is not (0, 0, 0, 1)
+    pub fn from_matrix4d(matrix: &Matrix4d) -> Option<Transform> {
+        let m = &matrix.rows;
+        if m[3] != [0.0, 0.0, 0.0, 1.0] {
+            return None;
+        }
+        Some(Transform {
+            linear: Matrix3d::from_rows([
+                [m[0][0], m[0][1], m[0][2]],
+                [m[1][0], m[1][1], m[1][2]],
+                [m[2][0], m[2][1], m[2][2]],
+            ]),
+            translation: Vector3d {x: m[0][3], y: m[1][3], z: m[2][3]},
+        })
+    }
+
+    pub fn to_matrix4d(self) -> Matrix4d {
+        let l = &self.linear.rows;
+        let t = self.translation;
+        Matrix4d::from_rows([
+            [l[0][0], l[0][1], l[0][2], t.x],
+            [l[1][0], l[1][1], l[1][2], t.y],
+            [l[2][0], l[2][1], l[2][2], t.z],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn inverse(&self) -> Option<Transform> {
+        let linear = self.linear.inverse()?;
+        Some(Transform {linear, translation: -(linear * self.translation)})
+    }
+
+    pub fn apply_point(&self, point: Point3d) -> Point3d {
+        Point3d {x: 0.0, y: 0.0, z: 0.0} + (self.linear * point.as_vector() + self.translation)
+    }
+
+    pub fn apply_vector(&self, vector: Vector3d) -> Vector3d {
+        self.linear * vector
+    }
+
+    // Normals are transformed by the inverse transpose, so that they stay perpendicular to surfaces;
+    // `None` if the transform is singular
+    pub fn normal_matrix(&self) -> Option<Matrix3d> {
+        Some(self.linear.inverse()?.transpose())
+    }
+
+    // Computes the normal matrix on every call; use `normal_matrix` to transform many normals
+    pub fn apply_normal(&self, normal: Vector3d) -> Option<Vector3d> {
+        (self.normal_matrix()? * normal).normalized()
+    }
+
+    // Returns `None` if the transform makes the triangle degenerate
+    pub fn apply_triangle(&self, tri: Triangle3d) -> Option<Triangle3d> {
+        Triangle3d::try_new(self.apply_point(tri.a), self.apply_point(tri.b), self.apply_point(tri.c))
+    }
+
+    // Returns `None` if the transform makes the parallelogram degenerate
+    pub fn apply_par(&self, par: Par3d) -> Option<Par3d> {
+        let (vec1, vec2) = par.vectors();
+        Par3d::try_new(self.apply_point(par.origin()), self.apply_vector(vec1), self.apply_vector(vec2))
+    }
+}
+
+// `a * b` applies `b` first and `a` second
+impl Mul for Transform {
+    type Output = Transform;
+
+    fn mul(self, other: Transform) -> Transform {
+        Transform {
+            linear: self.linear * other.linear,
+            translation: self.linear * other.translation + self.translation,
+        }
+    }
+}
+
+impl Mul<Point3d> for Transform {
+    type Output = Point3d;
+
+    fn mul(self, point: Point3d) -> Point3d {
+        self.apply_point(point)
+    }
+}
+
+impl Mul<Vector3d> for Transform {
+    type Output = Vector3d;
+
+    fn mul(self, vector: Vector3d) -> Vector3d {
+        self.apply_vector(vector)
+    }
+}
@@ -1,23 +1,60 @@
 extern crate sdl2;
-extern crate gcd;
 
+mod animation;
 mod clock;
+mod draw2d;
 mod render;
 mod geometry;
+mod import;
 mod linalg;
+mod mesh;
+mod polygon;
+mod profile;
+mod scene;
+mod shapes;
+mod texture;
+mod voxel;
 mod with;
 
-use crate::geometry::{Point, Point3d, BasicTriangle, Triangle, BasicPoint, Par3d};
-use crate::render::{RGB, Render, Renderer, ParFill, CoordsTranslator, TranslateCoords};
-use crate::clock::{Clock, EventsPerSecondTracker, ApproximateTimer};
+use crate::geometry::{Aabb, Angle, Cuboid, Point, Point3d, BasicTriangle, Triangle, BasicPoint, Par3d, Vector3d};
+use crate::render::{RGB, Camera, Render, Renderer, ParFill, CoordsTranslator, GouraudFill, TranslateCoords, Viewport};
+use crate::linalg::Quaternion;
+use crate::scene::{Scene, NodeId, NodeTransform, MeshDrawable, ParDrawable, PolygonDrawable};
+use crate::mesh::{Mesh, VertexColorShader};
+use crate::polygon::Polygon3d;
+use crate::voxel::{BlockId, BlockPosition, VoxelWorld};
+use crate::animation::{Animation, Easing, Interpolated, Playback, Track};
+use crate::clock::{EventsPerSecondTracker, FrameLimit, FrameLimiter, FrameStats, GameClock, GameLoop, Scheduler};
+use crate::draw2d::{FrameGraph, Paint, Path, Polygon};
+use crate::import::gltf::{GltfScene, load_gltf};
+use crate::import::obj::load_obj;
+use crate::import::ply::load_ply;
+use crate::import::stl::load_stl;
+use crate::import::svg::load_svg;
+use crate::profile::StageTiming;
+use crate::texture::TextureShader;
 use crate::with::With;
 
 use sdl2::{Sdl, VideoSubsystem, EventPump};
 use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use sdl2::mouse::MouseButton;
 use sdl2::video::Window;
 use std::error::Error;
+use std::env;
 use std::fmt::{self, Display, Formatter};
-use std::time::{Duration, Instant};
+use std::path::Path as FilePath;
+use core::f64::consts::PI;
+use std::time::Duration;
+
+
+const TRACE_PATH: &str = "trace.json";
+const FRAMES_PER_SECOND: f64 = 120.0;
+const FRAME_LIMIT: FrameLimit = FrameLimit::Hybrid {spin: Duration::from_millis(2)};
+const MIN_TIME_SCALE: f64 = 1.0 / 16.0;
+const MAX_TIME_SCALE: f64 = 16.0;
+const WINDOW_WIDTH: u32 = 800;
+const WINDOW_HEIGHT: u32 = 600;
 
 
 #[derive(Debug)]
@@ -53,6 +90,10 @@ struct SdlEnv {
 
 
 fn init_sdl() -> Result<SdlEnv, SdlError> {
+    // SDL reads this when it sets up presentation of the window surface, so VSync can only be turned on at startup
+    if FRAME_LIMIT == FrameLimit::VSync {
+        sdl2::hint::set("SDL_RENDER_VSYNC", "1");
+    }
     let context = sdl2::init()?;
     let video = context.video()?;
     Ok(SdlEnv { context, video })
@@ -70,66 +111,456 @@ fn make_window(sdl: &SdlEnv, title: &str, width: u32, height: u32) -> Result<Win
 }
 
 
-fn main_loop(window: &Window, event_pump: &mut EventPump) -> Result<(), SdlError> {
-    let mut clock = Clock::new();
-    let mut fps_tracker = EventsPerSecondTracker::new();
-    let mut approximate_timer = ApproximateTimer::new(Duration::from_secs(1));
+// Files given on the command line are added to the world: models spin with the triangle and SVG images are drawn
+// over the scene
+fn load_file(path: &FilePath, world: &mut SpinningTriangle, overlays: &mut Vec<Path>) -> Result<(), Box<dyn Error>> {
+    let name = path.file_stem().map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+    let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or("").to_ascii_lowercase();
+    match extension.as_str() {
+        "obj" => {
+            let model = load_obj(path)?;
+            report_skipped_faces(path, &model.skipped_faces);
+            world.add_model(&name, model.mesh);
+        },
+        "stl" => {
+            let model = load_stl(path)?;
+            report_skipped_faces(path, &model.skipped_faces);
+            world.add_model(&name, model.mesh);
+        },
+        "ply" => {
+            let model = load_ply(path)?;
+            report_skipped_faces(path, &model.skipped_faces);
+            world.add_model(&name, model.mesh);
+        },
+        "gltf" | "glb" => world.add_gltf(&name, &load_gltf(path)?),
+        "svg" => overlays.extend(load_svg(path)?.to_paths(BasicPoint {x: 0.0, y: 0.0}, 1.0)),
+        _ => return Err(format!("Unsupported file type: {}", path.display()).into()),
+    }
+    Ok(())
+}
+
+
+fn report_skipped_faces(path: &FilePath, skipped_faces: &[usize]) {
+    if !skipped_faces.is_empty() {
+        println!("Skipped {} degenerate faces of {}", skipped_faces.len(), path.display());
+    }
+}
+
 
-    let spinning_triangle = SpinningTriangle::new();
+fn main_loop(
+    window: &Window,
+    event_pump: &mut EventPump,
+    mut spinning_triangle: SpinningTriangle,
+    overlays: &[Path],
+) -> Result<(), SdlError> {
+    let mut frame_limiter = FrameLimiter::new(FRAMES_PER_SECOND, FRAME_LIMIT);
+    let mut scheduler = Scheduler::new();
+    scheduler.every(Duration::from_secs(1), FrameReport::print);
+    let mut report = FrameReport {
+        fps_tracker: EventsPerSecondTracker::new(),
+        frame_stats: FrameStats::new(240),
+        stages: Vec::new(),
+    };
+    let mut game_clock = GameClock::new();
+    let mut game_loop = GameLoop::new(60.0, 5);
+    let mut show_frame_graph = true;
+    let mut tick_duration = Duration::ZERO;
 
     loop {
         for event in event_pump.poll_iter() {
             match event {
                 Event::Quit {..} => return Ok(()),
+                Event::KeyDown {keycode: Some(Keycode::Space), repeat: false, ..} => {
+                    game_clock.set_paused(!game_clock.is_paused());
+                },
+                // Steps the simulation by exactly one fixed step while paused
+                Event::KeyDown {keycode: Some(Keycode::Period), ..} => game_clock.step(game_loop.step()),
+                Event::KeyDown {keycode: Some(keycode @ (Keycode::Minus | Keycode::Equals | Keycode::Num0)), ..} => {
+                    let scale = match keycode {
+                        Keycode::Minus => (game_clock.scale() / 2.0).max(MIN_TIME_SCALE),
+                        Keycode::Equals => (game_clock.scale() * 2.0).min(MAX_TIME_SCALE),
+                        _ => 1.0,
+                    };
+                    game_clock.set_scale(scale);
+                    println!("Time scale: {}", scale);
+                },
+                Event::KeyDown {keycode: Some(Keycode::G), repeat: false, ..} => show_frame_graph = !show_frame_graph,
+                Event::KeyDown {keycode: Some(Keycode::T), repeat: false, ..} => toggle_trace(),
+                Event::KeyDown {keycode: Some(Keycode::L), repeat: false, ..} => {
+                    let strategy = next_frame_limit(frame_limiter.strategy());
+                    frame_limiter.set_strategy(strategy);
+                    println!("Frame limit: {:?}", strategy);
+                },
+                Event::MouseButtonDown {mouse_btn: MouseButton::Left, x, y, ..} => {
+                    let (width, height) = window.size();
+                    spinning_triangle.pick(Point {x, y}, Viewport::new(width, height));
+                },
                 _ => {}
             }
         }
 
-        render::render_frame(&spinning_triangle, window.surface(event_pump)?)?;
-        fps_tracker.event();
-        let tick_duration = clock.tick(120.0);
-        if approximate_timer.update(tick_duration) != 0 {
-            let fps = fps_tracker.mean();
-            fps_tracker.reset();
-            println!("FPS: {}", fps);
+        // The simulation runs in game time, while frame statistics and timers keep to real time
+        let factor = game_loop.run_frame(game_clock.advance(tick_duration), |step| spinning_triangle.update(step));
+        spinning_triangle.interpolate(factor);
+        let (_, height) = window.size();
+        let frame_graph = FrameGraph {
+            stats: &report.frame_stats,
+            min: BasicPoint {x: 10.0, y: height as f64 - 70.0},
+            max: BasicPoint {x: 250.0, y: height as f64 - 10.0},
+            scale: Duration::from_millis(50),
+        };
+        let frame = Frame {
+            world: &spinning_triangle,
+            overlays,
+            frame_graph: show_frame_graph.then_some(frame_graph),
+        };
+        render::render_frame(&frame, window.surface(event_pump)?)?;
+        report.fps_tracker.event();
+        tick_duration = frame_limiter.wait();
+        report.frame_stats.record(tick_duration);
+        report.stages = profile::end_frame();
+        scheduler.update(tick_duration, &mut report);
+    }
+}
+
+
+// What the main loop measures, printed periodically
+struct FrameReport {
+    fps_tracker: EventsPerSecondTracker,
+    frame_stats: FrameStats,
+    stages: Vec<StageTiming>,
+}
+
+impl FrameReport {
+    fn print(&mut self) {
+        let fps = self.fps_tracker.mean();
+        self.fps_tracker.reset();
+        println!("FPS: {}", fps);
+        if let Some(summary) = self.frame_stats.summary() {
+            println!(
+                "Frame times: min {:?}, p50 {:?}, p95 {:?}, p99 {:?}, max {:?}",
+                summary.min, summary.p50, summary.p95, summary.p99, summary.max,
+            );
         }
+        let stages: Vec<String> = self.stages.iter().map(|stage| format!("{} {:?}", stage.name, stage.total)).collect();
+        println!("Last frame: {}", stages.join(", "));
+    }
+}
+
+
+// VSync is only offered if it was turned on at startup: otherwise presenting does not wait for the display, and the
+// VSync limit would not limit anything
+fn next_frame_limit(strategy: FrameLimit) -> FrameLimit {
+    match strategy {
+        FrameLimit::Hybrid {..} => FrameLimit::Sleep,
+        FrameLimit::Sleep if FRAME_LIMIT == FrameLimit::VSync => FrameLimit::VSync,
+        FrameLimit::Sleep | FrameLimit::VSync => FrameLimit::Uncapped,
+        FrameLimit::Uncapped => FrameLimit::Hybrid {spin: Duration::from_millis(2)},
+    }
+}
+
+
+// Starts recording a trace, or writes the one being recorded
+fn toggle_trace() {
+    if !profile::is_tracing() {
+        profile::start_trace();
+        println!("Recording a trace, press T again to save it");
+        return;
+    }
+    match profile::finish_trace(TRACE_PATH) {
+        Ok(()) => println!("Trace saved to {}", TRACE_PATH),
+        Err(error) => println!("Could not save the trace: {}", error),
     }
 }
 
 
 fn main() -> Result<(), Box<dyn Error>> {
+    let mut spinning_triangle = SpinningTriangle::new();
+    let mut overlays = Vec::new();
+    for path in env::args_os().skip(1) {
+        load_file(FilePath::new(&path), &mut spinning_triangle, &mut overlays)?;
+    }
+
     let sdl_env = init_sdl()?;
-    let window = make_window(&sdl_env, "My window", 800, 600)?;
+    let window = make_window(&sdl_env, "My window", WINDOW_WIDTH, WINDOW_HEIGHT)?;
 
     let mut event_pump = sdl_env.context.event_pump()?;
-    main_loop(&window, &mut event_pump)?;
+    main_loop(&window, &mut event_pump, spinning_triangle, &overlays)?;
 
     Ok(())
 }
 
 
+// Alternates between the outer and the inner radius, starting at the top
+fn star(center: BasicPoint<f64>, outer_radius: f64, inner_radius: f64, points: usize) -> Vec<BasicPoint<f64>> {
+    (0..2 * points)
+        .map(|index| {
+            let radius = if index % 2 == 0 { outer_radius } else { inner_radius };
+            let angle = index as f64 * PI / points as f64;
+            BasicPoint {x: center.x + radius * angle.sin(), y: center.y - radius * angle.cos()}
+        })
+        .collect()
+}
+
+
+// Bakes light coming from above into the vertex colours, as the renderer does no lighting of its own
+fn lit(mut mesh: Mesh, color: RGB) -> Mesh {
+    for vertex in mesh.vertices_mut() {
+        vertex.color = vertex.normal.map_or(color, |normal| lit_color(color, normal));
+    }
+    mesh
+}
+
+
+fn lit_color(color: RGB, normal: Vector3d) -> RGB {
+    // The Y axis points down
+    let level = (255.0 * (0.7 - 0.3 * normal.y)).round() as u8;
+    color.modulate(RGB::new(level, level, level))
+}
+
+
+const GRASS: BlockId = BlockId(1);
+const STONE: BlockId = BlockId(2);
+
+
+// A patch of ground with a few stone columns, in blocks above the ground layer at y = 0
+fn terrain() -> VoxelWorld {
+    let mut world = VoxelWorld::new(20.0);
+    for x in 0..20 {
+        for z in 0..15 {
+            world.set_block(BlockPosition::new(x, 0, z), GRASS);
+        }
+    }
+    for &(x, z, height) in &[(3, 4, 2), (15, 6, 3), (9, 11, 1)] {
+        for y in 1..=height {
+            world.set_block(BlockPosition::new(x, -y, z), STONE);
+        }
+    }
+    world
+}
+
+
+fn block_color(block: BlockId) -> RGB {
+    match block {
+        GRASS => RGB::new(90, 160, 70),
+        _ => RGB::new(140, 140, 150),
+    }
+}
+
+
 struct SpinningTriangle {
-    origin: Instant,
+    scene: Scene,
+    node: NodeId,
+    // Both rendering and picking look through this camera
+    camera: Camera,
+    spin: Animation<Angle>,
+    sway: Animation<Vector3d>,
+    state: Interpolated<NodeTransform>,
 }
 
 impl SpinningTriangle {
     pub fn new() -> SpinningTriangle {
-        SpinningTriangle {origin: Instant::now()}
+        let mut scene = Scene::new();
+        let transform = NodeTransform::from_translation(Vector3d {x: 0.0, y: 0.0, z: 200.0});
+        let node = scene.add_node(None, "spinning triangle", transform);
+        let a = Point3d {x: 100.0, y: 30.0, z: 0.0};
+        let par = Par3d::new(a, Vector3d {x: 0.0, y: -60.0, z: 0.0}, Vector3d {x: -200.0, y: 0.0, z: 0.0});
+        scene.attach(node, ParDrawable {par, filler_constructor: GradientParFillerConstructor {}});
+
+        let origin = Point3d {x: 0.0, y: 0.0, z: 0.0};
+        let transform = NodeTransform::from_translation(Vector3d {x: -200.0, y: 0.0, z: 250.0});
+        let sphere = scene.add_node(None, "sphere", transform);
+        let mesh = lit(shapes::sphere(origin, 40.0, 16, 8), RGB::new(220, 80, 60));
+        scene.attach(sphere, MeshDrawable {mesh, shader: VertexColorShader {}});
+        // Tilted towards the camera, which would otherwise see the torus edge-on
+        let tilt = Quaternion::from_axis_angle(Vector3d {x: 1.0, y: 0.0, z: 0.0}, Angle::from_degrees(60.0));
+        let transform = NodeTransform::from_translation(Vector3d {x: 200.0, y: 0.0, z: 250.0}).with_rotation(tilt);
+        let torus = scene.add_node(None, "torus", transform);
+        let mesh = lit(shapes::torus(origin, 40.0, 15.0, 24, 12), RGB::new(80, 160, 220));
+        scene.attach(torus, MeshDrawable {mesh, shader: VertexColorShader {}});
+
+        // A shelf of primitives behind the others. Round shapes grow down the Y axis from their base, so the cone
+        // stands on its tip.
+        let transform = NodeTransform::from_translation(Vector3d {x: 0.0, y: 60.0, z: 400.0});
+        let shelf = scene.add_node(None, "shelf", transform);
+        let floor = shapes::plane(
+            Point3d {x: -380.0, y: 0.0, z: -60.0},
+            Vector3d {x: 760.0, y: 0.0, z: 0.0},
+            Vector3d {x: 0.0, y: 0.0, z: 120.0},
+            8,
+            2,
+        );
+        let box_shape = Cuboid::new(
+            Point3d {x: -330.0, y: -50.0, z: -30.0},
+            Vector3d {x: 60.0, y: 0.0, z: 0.0},
+            Vector3d {x: 0.0, y: 50.0, z: 0.0},
+            Vector3d {x: 0.0, y: 0.0, z: 60.0},
+        );
+        let standing = |x: f64, height: f64| Point3d {x, y: -height, z: 0.0};
+        let meshes = [
+            (floor, RGB::new(120, 120, 130)),
+            (shapes::cuboid(&box_shape), RGB::new(200, 140, 60)),
+            (shapes::cylinder(standing(-150.0, 70.0), 25.0, 70.0, 16, true), RGB::new(90, 190, 90)),
+            (shapes::cone(standing(0.0, 70.0), 30.0, 70.0, 16, true), RGB::new(190, 90, 190)),
+            (shapes::frustum(standing(150.0, 50.0), 35.0, 20.0, 50.0, 16, true), RGB::new(220, 200, 70)),
+        ];
+        for (mesh, color) in meshes {
+            scene.attach(shelf, MeshDrawable {mesh: lit(mesh, color), shader: VertexColorShader {}});
+        }
+
+        // A concave outline with a hole, above the triangle
+        let transform = NodeTransform::from_translation(Vector3d {x: 0.0, y: -130.0, z: 250.0});
+        let star_node = scene.add_node(None, "star", transform);
+        let to_3d = |point: BasicPoint<f64>| Point3d {x: point.x, y: point.y, z: 0.0};
+        let outer = star(BasicPoint {x: 0.0, y: 0.0}, 50.0, 22.0, 5).into_iter().map(to_3d).collect();
+        let hole = star(BasicPoint {x: 0.0, y: 0.0}, 12.0, 12.0, 2).into_iter().map(to_3d).collect();
+        let polygon = Polygon3d::new(outer, vec![hole]);
+        scene.attach(star_node, PolygonDrawable {polygon, filler_constructor: GradientParFillerConstructor {}});
+
+        let transform = NodeTransform::from_translation(Vector3d {x: -200.0, y: 80.0, z: 100.0});
+        let ground = scene.add_node(None, "ground", transform);
+        for quad in terrain().mesh() {
+            let color = lit_color(block_color(quad.block), quad.direction.normal());
+            let filler_constructor = GouraudFill::parallelogram(color, color, color, color);
+            scene.attach(ground, ParDrawable {par: quad.face, filler_constructor});
+        }
+
+        let spin = Track::new()
+            .with_keyframe(Duration::ZERO, Angle::zero(), Easing::Linear)
+            .with_keyframe(Duration::from_secs_f64(2.0 * PI), Angle::zero() - Angle::circle(), Easing::Linear);
+        let sway = Track::new()
+            .with_keyframe(Duration::ZERO, Vector3d {x: 0.0, y: -20.0, z: 200.0}, Easing::SineInOut)
+            .with_keyframe(Duration::from_secs(2), Vector3d {x: 0.0, y: 20.0, z: 200.0}, Easing::SineInOut);
+        let mut spinning_triangle = SpinningTriangle {
+            scene,
+            node,
+            camera: Camera::new(),
+            spin: Animation::new(spin, Playback::Loop),
+            sway: Animation::new(sway, Playback::PingPong),
+            state: Interpolated::new(transform),
+        };
+        spinning_triangle.state = Interpolated::new(spinning_triangle.animated_transform());
+        spinning_triangle.interpolate(1.0);
+        spinning_triangle
+    }
+
+    // Scales the model to the size of the triangle and centres it on the spinning node
+    pub fn add_model(&mut self, name: &str, mesh: Mesh) {
+        if let Some(bounds) = Aabb::from_points(mesh.vertices().iter().map(|vertex| vertex.position)) {
+            let node = self.add_fitted_node(name, bounds);
+            self.scene.attach(node, MeshDrawable {mesh, shader: VertexColorShader {}});
+        }
+    }
+
+    // A scene with a camera is shown as authored and viewed through its first camera,
+    // otherwise it is fitted to the spinning node like any other model
+    pub fn add_gltf(&mut self, name: &str, gltf: &GltfScene) {
+        let instances = gltf.instances();
+        let aspect_ratio = f64::from(WINDOW_WIDTH) / f64::from(WINDOW_HEIGHT);
+        let node = match gltf.renderer_cameras(aspect_ratio).into_iter().next() {
+            Some(camera) => {
+                self.camera = camera;
+                self.scene.add_node(None, name, NodeTransform::identity())
+            },
+            None => {
+                let positions = instances.iter()
+                    .flat_map(|instance| instance.mesh.vertices().iter().map(|vertex| vertex.position));
+                match Aabb::from_points(positions) {
+                    Some(bounds) => self.add_fitted_node(name, bounds),
+                    None => return,
+                }
+            },
+        };
+        for instance in instances {
+            match instance.material.and_then(|material| gltf.base_color_texture(material)) {
+                Some(texture) => {
+                    let shader = TextureShader::new(texture.clone());
+                    self.scene.attach(node, MeshDrawable {mesh: instance.mesh, shader});
+                },
+                None => self.scene.attach(node, MeshDrawable {mesh: instance.mesh, shader: VertexColorShader {}}),
+            }
+        }
+    }
+
+    fn add_fitted_node(&mut self, name: &str, bounds: Aabb) -> NodeId {
+        let size = bounds.size();
+        let scale = 100.0 / size.x.max(size.y).max(size.z).max(f64::EPSILON);
+        let translation = (Point3d {x: 0.0, y: 0.0, z: 0.0} - bounds.center()) * scale;
+        let scale = Vector3d {x: scale, y: scale, z: scale};
+        let transform = NodeTransform::from_translation(translation).with_scale(scale);
+        self.scene.add_node(Some(self.node), name, transform)
+    }
+
+    // Advances the simulation by one fixed step
+    pub fn update(&mut self, step: Duration) {
+        self.spin.advance(step);
+        self.sway.advance(step);
+        let transform = self.animated_transform();
+        self.state.push(transform);
+    }
+
+    // Places the triangle between its last two simulated states for rendering
+    pub fn interpolate(&mut self, factor: f64) {
+        self.scene.node_mut(self.node).transform = self.state.blend(factor);
+    }
+
+    fn animated_transform(&self) -> NodeTransform {
+        let mut transform = self.state.current();
+        let axis = Vector3d {x: 0.0, y: 1.0, z: 0.0};
+        if let Some(angle) = self.spin.value() {
+            transform.rotation = Quaternion::from_axis_angle(axis, angle);
+        }
+        if let Some(translation) = self.sway.value() {
+            transform.translation = translation;
+        }
+        transform
+    }
+
+    pub fn pick(&self, point: Point, viewport: Viewport) {
+        let ray = self.camera.unproject(point, viewport);
+        match self.scene.pick(&ray) {
+            Some(hit) => println!("Picked \"{}\" at {:?}", self.scene.node(hit.node).name, hit.point),
+            None => println!("Nothing under the cursor"),
+        }
     }
 }
 
 impl Render for SpinningTriangle {
     fn render<'a>(&self, renderer: &mut Renderer<'a>) {
-        let t = self.origin.elapsed().as_secs_f64();
-        let a = Point3d {x:  100.0 * t.cos(), y:  30.0, z: 200.0 + 100.0 * t.sin()};
-        let b = Point3d {x:  100.0 * t.cos(), y: -30.0, z: 200.0 + 100.0 * t.sin()};
-        let c = Point3d {x: -100.0 * t.cos(), y:  30.0, z: 200.0 - 100.0 * t.sin()};
-        let par = Par3d::new(a, b - a, c - a);
-        renderer.fill_parallelogram(par, GradientParFillerConstructor{});
+        renderer.set_camera(self.camera.clone());
+        self.scene.render(renderer);
+    }
+}
+
+
+// The world with overlays and the frame graph drawn on top
+struct Frame<'a> {
+    world: &'a SpinningTriangle,
+    overlays: &'a [Path],
+    frame_graph: Option<FrameGraph<'a>>,
+}
+
+impl Render for Frame<'_> {
+    fn render<'a>(&self, renderer: &mut Renderer<'a>) {
+        self.world.render(renderer);
+        for overlay in self.overlays {
+            renderer.draw_overlay(overlay);
+        }
+        let center = BasicPoint {x: 30.0, y: 30.0};
+        let badge = Polygon {
+            outer: star(center, 20.0, 9.0, 5),
+            holes: vec![star(center, 5.0, 5.0, 2)],
+            paint: Paint::Fill(RGB::new(240, 200, 40)),
+        };
+        renderer.draw_overlay(&badge);
+        if let Some(frame_graph) = &self.frame_graph {
+            renderer.draw_overlay(frame_graph);
+        }
     }
 }
 
 
+#[derive(Clone, Copy)]
 struct GradientParFillerConstructor {}
 
 impl With<Triangle> for GradientParFillerConstructor {
@@ -0,0 +1,132 @@
+use crate::geometry::{BasicPoint, Point3d, Triangle, Triangle3d, Vector3d};
+use crate::render::{RGB, GouraudFill, GouraudFiller};
+use crate::with::With;
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Vertex {
+    pub position: Point3d,
+    pub normal: Option<Vector3d>,
+    pub uv: Option<BasicPoint<f64>>,
+    pub color: RGB,
+}
+
+impl Vertex {
+    pub fn new(position: Point3d) -> Vertex {
+        Vertex {
+            position,
+            normal: None,
+            uv: None,
+            color: RGB::new(255, 255, 255),
+        }
+    }
+
+    pub fn with_color(self, color: RGB) -> Vertex {
+        Vertex {color, ..self}
+    }
+}
+
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Mesh {
+    vertices: Vec<Vertex>,
+    indices: Vec<(usize, usize, usize)>,
+}
+
+impl Mesh {
+    pub fn new() -> Mesh {
+        Mesh::default()
+    }
+
+    pub fn from_buffers(vertices: Vec<Vertex>, indices: Vec<(usize, usize, usize)>) -> Mesh {
+        Mesh::try_from_buffers(vertices, indices).expect("Mesh indices must point into the vertex buffer")
+    }
+
+    pub fn try_from_buffers(vertices: Vec<Vertex>, indices: Vec<(usize, usize, usize)>) -> Option<Mesh> {
+        let count = vertices.len();
+        if indices.iter().all(|&(i, j, k)| i < count && j < count && k < count) {
+            Some(Mesh {vertices, indices})
+        } else {
+            None
+        }
+    }
+
+    pub fn vertices(&self) -> &[Vertex] {
+        &self.vertices
+    }
+
+    // Vertices can be changed in place, but not added or removed, so that indices stay valid
+    pub fn vertices_mut(&mut self) -> &mut [Vertex] {
+        &mut self.vertices
+    }
+
+    pub fn indices(&self) -> &[(usize, usize, usize)] {
+        &self.indices
+    }
+
+    pub fn push_vertex(&mut self, vertex: Vertex) -> usize {
+        self.vertices.push(vertex);
+        self.vertices.len() - 1
+    }
+
+    pub fn push_triangle(&mut self, i: usize, j: usize, k: usize) {
+        let count = self.vertices.len();
+        assert!(i < count && j < count && k < count, "Mesh indices must point into the vertex buffer");
+        self.indices.push((i, j, k));
+    }
+
+    pub fn append(&mut self, other: &Mesh) {
+        let offset = self.vertices.len();
+        self.vertices.extend_from_slice(&other.vertices);
+        self.indices.extend(other.indices.iter().map(|&(i, j, k)| (i + offset, j + offset, k + offset)));
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len()
+    }
+
+    pub fn vertices_of(&self, index: usize) -> (Vertex, Vertex, Vertex) {
+        let (i, j, k) = self.indices[index];
+        (self.vertices[i], self.vertices[j], self.vertices[k])
+    }
+
+    // Degenerate triangles are silently skipped
+    pub fn triangles(&self) -> impl Iterator<Item = Triangle3d> + '_ {
+        (0..self.indices.len()).filter_map(move |index| {
+            let (a, b, c) = self.vertices_of(index);
+            Triangle3d::try_new(a.position, b.position, c.position)
+        })
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeshTriangle {
+    pub on_screen: Triangle,
+    pub vertices: (Vertex, Vertex, Vertex),
+}
+
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VertexColorShader {}
+
+impl With<MeshTriangle> for VertexColorShader {
+    type Output = GouraudFiller;
+
+    fn with(self, tri: MeshTriangle) -> GouraudFiller {
+        let (a, b, c) = tri.vertices;
+        GouraudFill::triangle(a.color, b.color, c.color).with(tri.on_screen)
+    }
+}
+
+
+#[derive(Debug, Clone, Copy)]
+pub struct FlatShader<Constructor>(pub Constructor);
+
+impl<Constructor: With<Triangle>> With<MeshTriangle> for FlatShader<Constructor> {
+    type Output = Constructor::Output;
+
+    fn with(self, tri: MeshTriangle) -> Self::Output {
+        self.0.with(tri.on_screen)
+    }
+}
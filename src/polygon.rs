@@ -0,0 +1,385 @@
+use crate::geometry::{
+    orient2d,
+    point_in_triangle,
+    BasicPoint,
+    Cross,
+    Dot,
+    Norm,
+    Normalize,
+    Par3d,
+    Point3d,
+    Triangle3d,
+    Vector3d,
+};
+use crate::linalg::Transform;
+
+use std::cmp::Ordering;
+
+
+// Splits a simple polygon with simple holes into triangles by ear clipping. Rings may be given in any winding;
+// holes must lie inside the outer ring and must not touch each other. Triangles index into the outer ring followed by
+// all holes in order. Returns `None` if a ring has fewer than 3 vertices or the polygon turns out not to be simple.
+pub fn triangulate(
+    outer: &[BasicPoint<f64>],
+    holes: &[Vec<BasicPoint<f64>>]
+) -> Option<Vec<(usize, usize, usize)>> {
+    if outer.len() < 3 || holes.iter().any(|hole| hole.len() < 3) {
+        return None;
+    }
+    let mut points = outer.to_vec();
+    for hole in holes {
+        points.extend_from_slice(hole);
+    }
+
+    // The outer ring goes counterclockwise and holes go clockwise, so that bridged rings stay consistent
+    let mut ring: Vec<usize> = (0..outer.len()).collect();
+    if signed_area(&points, &ring) < 0.0 {
+        ring.reverse();
+    }
+    let mut hole_rings = Vec::with_capacity(holes.len());
+    let mut start = outer.len();
+    for hole in holes {
+        let mut hole_ring: Vec<usize> = (start..start + hole.len()).collect();
+        if signed_area(&points, &hole_ring) > 0.0 {
+            hole_ring.reverse();
+        }
+        hole_rings.push(hole_ring);
+        start += hole.len();
+    }
+
+    // Holes closer to the right are bridged first, so that later bridges cannot cross earlier holes
+    let rightmost = |hole: &Vec<usize>| {
+        hole.iter().map(|&index| points[index].x).fold(f64::NEG_INFINITY, f64::max)
+    };
+    hole_rings.sort_by(|a, b| rightmost(b).total_cmp(&rightmost(a)));
+    for hole in hole_rings {
+        ring = bridge_hole(&points, ring, &hole)?;
+    }
+
+    clip_ears(&points, ring)
+}
+
+
+fn signed_area(points: &[BasicPoint<f64>], ring: &[usize]) -> f64 {
+    let mut area = 0.0;
+    for (position, &index) in ring.iter().enumerate() {
+        let (p, q) = (points[index], points[ring[(position + 1) % ring.len()]]);
+        area += p.x * q.y - q.x * p.y;
+    }
+    area / 2.0
+}
+
+
+// Connects the hole's rightmost vertex with a vertex of the ring visible from it, walking around the hole in between
+fn bridge_hole(points: &[BasicPoint<f64>], ring: Vec<usize>, hole: &[usize]) -> Option<Vec<usize>> {
+    let hole_start = (0..hole.len()).max_by(|&a, &b| points[hole[a]].x.total_cmp(&points[hole[b]].x))?;
+    let m = points[hole[hole_start]];
+
+    // Cast a ray from `m` to the right and find the nearest edge it hits
+    let mut nearest: Option<(f64, usize)> = None;
+    for position in 0..ring.len() {
+        let (p, q) = (points[ring[position]], points[ring[(position + 1) % ring.len()]]);
+        if (p.y < m.y && q.y < m.y) || (p.y > m.y && q.y > m.y) {
+            continue;
+        }
+        let x = if p.y == q.y {
+            p.x.min(q.x)
+        } else {
+            p.x + (m.y - p.y) * (q.x - p.x) / (q.y - p.y)
+        };
+        if x >= m.x && nearest.is_none_or(|(nearest_x, _)| x < nearest_x) {
+            nearest = Some((x, position));
+        }
+    }
+    let (hit_x, edge) = nearest?;
+    let hit = BasicPoint {x: hit_x, y: m.y};
+
+    // The edge endpoint further to the right is a candidate; reflex vertices inside the triangle (m, hit, candidate)
+    // would block it, in which case the one with the smallest angle to the ray is visible instead
+    let next = (edge + 1) % ring.len();
+    let mut candidate = if points[ring[edge]].x > points[ring[next]].x { edge } else { next };
+    let candidate_point = points[ring[candidate]];
+    if candidate_point.x != hit.x || candidate_point.y != hit.y {
+        let mut best_tangent = f64::INFINITY;
+        for position in 0..ring.len() {
+            let point = points[ring[position]];
+            if position == candidate || point.x < m.x || !point_in_triangle(point, m, hit, candidate_point) {
+                continue;
+            }
+            let tangent = (point.y - m.y).abs() / (point.x - m.x);
+            if tangent < best_tangent {
+                best_tangent = tangent;
+                candidate = position;
+            }
+        }
+    }
+
+    let mut result = Vec::with_capacity(ring.len() + hole.len() + 2);
+    result.extend_from_slice(&ring[..=candidate]);
+    result.extend(hole[hole_start..].iter().chain(hole[..=hole_start].iter()));
+    result.extend_from_slice(&ring[candidate..]);
+    Some(result)
+}
+
+
+fn clip_ears(points: &[BasicPoint<f64>], mut ring: Vec<usize>) -> Option<Vec<(usize, usize, usize)>> {
+    let mut triangles = Vec::with_capacity(ring.len().saturating_sub(2));
+    let mut position = 0;
+    let mut attempts = 0;
+    while ring.len() > 3 {
+        let length = ring.len();
+        let prev = ring[(position + length - 1) % length];
+        let (current, next) = (ring[position], ring[(position + 1) % length]);
+        match orient2d(points[prev], points[current], points[next]) {
+            Ordering::Greater if is_ear(points, &ring, prev, current, next) => {
+                triangles.push((prev, current, next));
+                ring.remove(position);
+                attempts = 0;
+            },
+            // Collinear vertices bound no area and are simply dropped
+            Ordering::Equal => {
+                ring.remove(position);
+                attempts = 0;
+            },
+            _ => {
+                position += 1;
+                attempts += 1;
+                if attempts > ring.len() {
+                    // Every vertex has been tried since the last ear, so the polygon is not simple
+                    return None;
+                }
+            },
+        }
+        position %= ring.len();
+    }
+    match orient2d(points[ring[0]], points[ring[1]], points[ring[2]]) {
+        Ordering::Greater => triangles.push((ring[0], ring[1], ring[2])),
+        Ordering::Equal => {},
+        // An inverted remainder means that the rings intersect themselves
+        Ordering::Less => return None,
+    }
+    Some(triangles)
+}
+
+
+// Vertices duplicated by bridges, or coinciding with the ear's corners, do not block it
+fn is_ear(points: &[BasicPoint<f64>], ring: &[usize], prev: usize, current: usize, next: usize) -> bool {
+    let (a, b, c) = (points[prev], points[current], points[next]);
+    let coincides = |p: BasicPoint<f64>, q: BasicPoint<f64>| p.x == q.x && p.y == q.y;
+    ring.iter().all(|&index| {
+        let point = points[index];
+        index == prev || index == current || index == next
+            || coincides(point, a) || coincides(point, b) || coincides(point, c)
+            || !point_in_triangle(point, a, b, c)
+    })
+}
+
+
+// A flat polygon in 3D, possibly with holes, kept together with its triangulation
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polygon3d {
+    outer: Vec<Point3d>,
+    holes: Vec<Vec<Point3d>>,
+    normal: Vector3d,
+    triangles: Vec<Triangle3d>,
+}
+
+impl Polygon3d {
+    pub fn new(outer: Vec<Point3d>, holes: Vec<Vec<Point3d>>) -> Polygon3d {
+        Polygon3d::try_new(outer, holes).expect("The polygon must be planar and simple")
+    }
+
+    pub fn try_new(outer: Vec<Point3d>, holes: Vec<Vec<Point3d>>) -> Option<Polygon3d> {
+        let normal = newell_normal(&outer)?;
+        let all_points = || outer.iter().chain(holes.iter().flatten());
+        let extent = all_points().map(|&point| (point - outer[0]).norm()).fold(0.0, f64::max);
+        if all_points().any(|&point| normal.dot(&(point - outer[0])).abs() > 1e-9 * extent) {
+            // Not planar
+            return None;
+        }
+
+        let project = dominant_projection(normal);
+        let outer_2d: Vec<_> = outer.iter().map(|&point| project(point)).collect();
+        let holes_2d: Vec<Vec<_>> = holes
+            .iter()
+            .map(|hole| hole.iter().map(|&point| project(point)).collect())
+            .collect();
+        let indices = triangulate(&outer_2d, &holes_2d)?;
+
+        let vertices: Vec<Point3d> = all_points().copied().collect();
+        let triangles = indices
+            .into_iter()
+            .filter_map(|(a, b, c)| Triangle3d::try_new(vertices[a], vertices[b], vertices[c]))
+            .collect();
+        Some(Polygon3d {outer, holes, normal, triangles})
+    }
+
+    pub fn outer(&self) -> &[Point3d] {
+        &self.outer
+    }
+
+    pub fn holes(&self) -> &[Vec<Point3d>] {
+        &self.holes
+    }
+
+    // Unit normal following the outer ring's winding by the right-hand rule
+    pub fn normal(&self) -> Vector3d {
+        self.normal
+    }
+
+    pub fn triangles(&self) -> &[Triangle3d] {
+        &self.triangles
+    }
+
+    // Keeps the triangulation, as an affine transform keeps the polygon simple; `None` if it flattens the polygon
+    pub fn transformed(&self, transform: &Transform) -> Option<Polygon3d> {
+        let outer: Vec<Point3d> = self.outer.iter().map(|&point| transform.apply_point(point)).collect();
+        let holes = self
+            .holes
+            .iter()
+            .map(|hole| hole.iter().map(|&point| transform.apply_point(point)).collect())
+            .collect();
+        let normal = newell_normal(&outer)?;
+        let triangles = self.triangles.iter().filter_map(|&tri| transform.apply_triangle(tri)).collect();
+        Some(Polygon3d {outer, holes, normal, triangles})
+    }
+}
+
+impl From<Par3d> for Polygon3d {
+    fn from(par: Par3d) -> Polygon3d {
+        let (vec1, vec2) = par.vectors();
+        let origin = par.origin();
+        Polygon3d::new(vec![origin, origin + vec1, origin + vec1 + vec2, origin + vec2], Vec::new())
+    }
+}
+
+
+// Triangulates a possibly concave face which need not be exactly planar, keeping the winding of `ring`. Returns
+// `None` if the face is degenerate or not simple.
+pub fn triangulate_face(ring: &[Point3d]) -> Option<Vec<(usize, usize, usize)>> {
+    let project = dominant_projection(newell_normal(ring)?);
+    let projected: Vec<_> = ring.iter().map(|&point| project(point)).collect();
+    let triangles = triangulate(&projected, &[])?;
+    // `triangulate` winds triangles counterclockwise, which reverses faces whose projection runs clockwise
+    let all: Vec<usize> = (0..ring.len()).collect();
+    if signed_area(&projected, &all) < 0.0 {
+        Some(triangles.into_iter().map(|(a, b, c)| (a, c, b)).collect())
+    } else {
+        Some(triangles)
+    }
+}
+
+
+// Dropping the dominant axis of the normal projects a polygon without flattening it
+fn dominant_projection(normal: Vector3d) -> fn(Point3d) -> BasicPoint<f64> {
+    let (x, y, z) = (normal.x.abs(), normal.y.abs(), normal.z.abs());
+    if x >= y && x >= z {
+        |p| BasicPoint {x: p.y, y: p.z}
+    } else if y >= z {
+        |p| BasicPoint {x: p.z, y: p.x}
+    } else {
+        |p| BasicPoint {x: p.x, y: p.y}
+    }
+}
+
+
+// Newell's method gives a robust normal even for concave polygons; coordinates are taken relative to the first
+// vertex to keep precision far from the origin
+fn newell_normal(ring: &[Point3d]) -> Option<Vector3d> {
+    if ring.len() < 3 {
+        return None;
+    }
+    let mut normal = Vector3d {x: 0.0, y: 0.0, z: 0.0};
+    for (index, &p) in ring.iter().enumerate() {
+        let q = ring[(index + 1) % ring.len()];
+        normal = normal + (p - ring[0]).cross(&(q - ring[0]));
+    }
+    normal.normalized()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points(coords: &[(f64, f64)]) -> Vec<BasicPoint<f64>> {
+        coords.iter().map(|&(x, y)| BasicPoint {x, y}).collect()
+    }
+
+    // Checks that every triangle goes counterclockwise and returns their total area
+    fn covered_area(points: &[BasicPoint<f64>], triangles: &[(usize, usize, usize)]) -> f64 {
+        triangles
+            .iter()
+            .map(|&(a, b, c)| {
+                let area = signed_area(points, &[a, b, c]);
+                assert!(area > 0.0, "Triangle {:?} is not counterclockwise", (a, b, c));
+                area
+            })
+            .sum()
+    }
+
+    fn covers(points: &[BasicPoint<f64>], triangles: &[(usize, usize, usize)], point: BasicPoint<f64>) -> bool {
+        triangles.iter().any(|&(a, b, c)| point_in_triangle(point, points[a], points[b], points[c]))
+    }
+
+    #[test]
+    fn concave_polygon_is_covered_exactly() {
+        // An L shape given clockwise, with a reflex vertex at (1, 1)
+        let outer = points(&[(0.0, 0.0), (0.0, 2.0), (2.0, 2.0), (2.0, 1.0), (1.0, 1.0), (1.0, 0.0)]);
+        let triangles = triangulate(&outer, &[]).unwrap();
+        assert_eq!(triangles.len(), 4);
+        assert_eq!(covered_area(&outer, &triangles), 3.0);
+        assert!(!covers(&outer, &triangles, BasicPoint {x: 1.5, y: 0.5}));
+    }
+
+    #[test]
+    fn hole_is_left_uncovered() {
+        let outer = points(&[(0.0, 0.0), (4.0, 0.0), (4.0, 4.0), (0.0, 4.0)]);
+        let hole = points(&[(1.0, 1.0), (3.0, 1.0), (3.0, 3.0), (1.0, 3.0)]);
+        let triangles = triangulate(&outer, std::slice::from_ref(&hole)).unwrap();
+        let all: Vec<_> = outer.iter().chain(&hole).copied().collect();
+        assert_eq!(triangles.len(), 8);
+        assert_eq!(covered_area(&all, &triangles), 12.0);
+        assert!(!covers(&all, &triangles, BasicPoint {x: 2.0, y: 2.0}));
+        assert!(covers(&all, &triangles, BasicPoint {x: 0.5, y: 2.0}));
+    }
+
+    #[test]
+    fn collinear_vertices_add_no_triangles() {
+        let outer = points(&[(0.0, 0.0), (1.0, 0.0), (2.0, 0.0), (2.0, 2.0), (1.0, 2.0), (0.0, 2.0)]);
+        let triangles = triangulate(&outer, &[]).unwrap();
+        assert_eq!(covered_area(&outer, &triangles), 4.0);
+    }
+
+    #[test]
+    fn degenerate_rings_give_no_triangles() {
+        let line = points(&[(0.0, 0.0), (1.0, 1.0), (2.0, 2.0), (3.0, 3.0)]);
+        assert_eq!(triangulate(&line, &[]), Some(Vec::new()));
+        assert_eq!(triangulate(&line[..2], &[]), None);
+
+        let line_3d: Vec<_> = line.iter().map(|point| Point3d {x: point.x, y: point.y, z: 1.0}).collect();
+        assert_eq!(Polygon3d::try_new(line_3d.clone(), Vec::new()), None);
+        assert_eq!(triangulate_face(&line_3d), None);
+    }
+
+    #[test]
+    fn self_intersecting_polygon_is_rejected() {
+        let bowtie = points(&[(0.0, 0.0), (2.0, 2.0), (2.0, 0.0), (0.0, 2.0)]);
+        assert_eq!(triangulate(&bowtie, &[]), None);
+    }
+
+    #[test]
+    fn face_triangles_keep_its_winding() {
+        let face = [
+            Point3d {x: 0.0, y: 0.0, z: 0.0},
+            Point3d {x: 0.0, y: 2.0, z: 0.0},
+            Point3d {x: 2.0, y: 2.0, z: 0.0},
+            Point3d {x: 2.0, y: 0.0, z: 0.0},
+        ];
+        let normal = newell_normal(&face).unwrap();
+        for (a, b, c) in triangulate_face(&face).unwrap() {
+            let triangle_normal = (face[b] - face[a]).cross(&(face[c] - face[a]));
+            assert!(triangle_normal.dot(&normal) > 0.0);
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+
+// Timings are collected per thread: a `scope` guard measures the stage named after it until it is dropped, and
+// `end_frame` hands out the totals since the previous frame. Trace events are only kept while a trace is recorded.
+
+
+thread_local! {
+    static PROFILER: RefCell<Profiler> = RefCell::new(Profiler::new());
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StageTiming {
+    pub name: &'static str,
+    pub total: Duration,
+    pub calls: u32,
+}
+
+
+#[must_use = "The stage is measured until the scope is dropped"]
+pub struct Scope {
+    name: &'static str,
+    // `None` for scopes nested in a scope of the same stage, which is already being measured
+    start: Option<Instant>,
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        if let Some(start) = self.start {
+            let end = Instant::now();
+            PROFILER.with(|profiler| profiler.borrow_mut().record(self.name, start, end));
+        }
+    }
+}
+
+
+pub fn scope(name: &'static str) -> Scope {
+    let nested = PROFILER.with(|profiler| {
+        let mut profiler = profiler.borrow_mut();
+        let nested = profiler.active.contains(&name);
+        if !nested {
+            profiler.active.push(name);
+        }
+        nested
+    });
+    Scope {name, start: if nested { None } else { Some(Instant::now()) }}
+}
+
+
+// Returns the time spent in each stage since the previous call, in order of first use within the frame
+pub fn end_frame() -> Vec<StageTiming> {
+    PROFILER.with(|profiler| {
+        let mut profiler = profiler.borrow_mut();
+        let now = Instant::now();
+        let frame_start = profiler.frame_start;
+        profiler.trace_event("frame", frame_start, now);
+        profiler.frame_start = now;
+        std::mem::take(&mut profiler.stages)
+    })
+}
+
+
+// Starts recording trace events, discarding any recorded before
+pub fn start_trace() {
+    PROFILER.with(|profiler| profiler.borrow_mut().trace = Some(Vec::new()));
+}
+
+
+pub fn is_tracing() -> bool {
+    PROFILER.with(|profiler| profiler.borrow().trace.is_some())
+}
+
+
+// Stops recording and writes the events in the Chrome Trace Event format, as read by chrome://tracing and Perfetto
+pub fn finish_trace(path: impl AsRef<Path>) -> io::Result<()> {
+    let events = PROFILER.with(|profiler| profiler.borrow_mut().trace.take()).unwrap_or_default();
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(writer, "{{\"traceEvents\": [")?;
+    for (index, event) in events.iter().enumerate() {
+        let separator = if index + 1 == events.len() { "" } else { "," };
+        writeln!(
+            writer,
+            concat!(
+                "  {{\"name\": \"{}\", \"cat\": \"{}\", \"ph\": \"X\", ",
+                "\"ts\": {:.3}, \"dur\": {:.3}, \"pid\": 1, \"tid\": 1}}{}",
+            ),
+            escape(event.name),
+            if event.name == "frame" { "frame" } else { "stage" },
+            event.start.as_secs_f64() * 1e6,
+            event.duration.as_secs_f64() * 1e6,
+            separator,
+        )?;
+    }
+    writeln!(writer, "], \"displayTimeUnit\": \"ms\"}}")?;
+    writer.flush()
+}
+
+
+struct TraceEvent {
+    name: &'static str,
+    // Since the profiler was created
+    start: Duration,
+    duration: Duration,
+}
+
+
+struct Profiler {
+    origin: Instant,
+    frame_start: Instant,
+    active: Vec<&'static str>,
+    stages: Vec<StageTiming>,
+    trace: Option<Vec<TraceEvent>>,
+}
+
+impl Profiler {
+    fn new() -> Profiler {
+        let now = Instant::now();
+        Profiler {origin: now, frame_start: now, active: Vec::new(), stages: Vec::new(), trace: None}
+    }
+
+    fn record(&mut self, name: &'static str, start: Instant, end: Instant) {
+        if let Some(position) = self.active.iter().rposition(|&active| active == name) {
+            self.active.remove(position);
+        }
+        let duration = end - start;
+        match self.stages.iter_mut().find(|stage| stage.name == name) {
+            Some(stage) => {
+                stage.total += duration;
+                stage.calls += 1;
+            },
+            None => self.stages.push(StageTiming {name, total: duration, calls: 1}),
+        }
+        self.trace_event(name, start, end);
+    }
+
+    fn trace_event(&mut self, name: &'static str, start: Instant, end: Instant) {
+        let origin = self.origin;
+        if let Some(trace) = &mut self.trace {
+            trace.push(TraceEvent {name, start: start - origin, duration: end - start});
+        }
+    }
+}
+
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
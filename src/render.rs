@@ -4,6 +4,7 @@ use crate::geometry::{
     BasicPoint,
     BasicTriangle,
     BasicVector,
+    Cuboid,
     GluedTriangle,
     HorizontalSegment,
     Line,
@@ -11,11 +12,17 @@ use crate::geometry::{
     Par3d,
     Point,
     Point3d,
+    Ray3d,
     Triangle,
     Triangle3d,
     Triangular,
+    Vector3d,
 };
-use crate::linalg::{Matrix2d, Basis};
+use crate::linalg::{Matrix2d, Basis, Transform};
+use crate::mesh::{Mesh, MeshTriangle};
+use crate::polygon::{triangulate, Polygon3d};
+use crate::profile;
+use crate::scene::Scene;
 use crate::with::With;
 use super::SdlError;
 
@@ -30,7 +37,10 @@ pub fn render_frame<'a>(
     renderable: &impl Render,
     mut surface_ref: WindowSurfaceRef<'a>
 ) -> Result<(), SdlError> {
-    surface_ref.fill_rect(None, Color::BLACK)?;
+    {
+        let _scope = profile::scope("clear");
+        surface_ref.fill_rect(None, Color::BLACK)?;
+    }
     let width = surface_ref.width();
     let height = surface_ref.height();
 
@@ -48,6 +58,7 @@ pub fn render_frame<'a>(
         let mut renderer = Renderer::new(rasterizer, width, height);
         renderable.render(&mut renderer);
     });
+    let _scope = profile::scope("present");
     surface_ref.finish()?;
     Ok(())
 }
@@ -64,9 +75,24 @@ impl RGB {
     pub fn new(r: u8, g: u8, b: u8) -> RGB {
         RGB { r, g, b }
     }
+
+    pub fn modulate(self, other: RGB) -> RGB {
+        let channel = |a: u8, b: u8| ((a as u16 * b as u16 + 127) / 255) as u8;
+        RGB::new(channel(self.r, other.r), channel(self.g, other.g), channel(self.b, other.b))
+    }
+
+    fn components(self) -> [f64; 3] {
+        [self.r as f64, self.g as f64, self.b as f64]
+    }
+
+    fn from_components(components: [f64; 3]) -> RGB {
+        let channel = |x: f64| x.round().max(0.0).min(255.0) as u8;
+        RGB::new(channel(components[0]), channel(components[1]), channel(components[2]))
+    }
 }
 
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct Camera {
     position: Point3d,
     azimuth: Angle,
@@ -78,13 +104,28 @@ pub struct Camera {
 
 impl Camera {
     pub fn new() -> Camera {
-        let hfov = Angle::from_degrees(100.0);
+        Camera::with_parameters(
+            Point3d{x: 0.0, y: 0.0, z: 0.0},
+            Angle::quarter_circle(),
+            Angle::zero(),
+            Angle::from_degrees(100.0),
+            Angle::from_degrees(70.0),
+        )
+    }
+
+    pub fn with_parameters(
+        position: Point3d,
+        azimuth: Angle,
+        vertical_angle: Angle,
+        hfov: Angle,
+        vfov: Angle
+    ) -> Camera {
         Camera {
-            position: Point3d{x: 0.0, y: 0.0, z: 0.0},
-            azimuth: Angle::quarter_circle(),
-            vertical_angle: Angle::zero(),
+            position,
+            azimuth,
+            vertical_angle,
             hfov,
-            vfov: Angle::from_degrees(70.0),
+            vfov,
             hfov_half_cot: (hfov / 2.0).as_radians().tan().recip(),
         }
     }
@@ -122,6 +163,24 @@ impl Camera {
         let coord_y = vertical_angle / self.vfov + 0.5;
         (BasicPoint{x: coord_x, y: coord_y}, vector.norm())
     }
+
+    // The inverse of `translate`: the ray from the camera through all points projected onto the given screen point
+    pub fn unproject(&self, point: Point, viewport: Viewport) -> Ray3d {
+        let viewport_agnostic_point = viewport.untranslate(point);
+        let tan_relative_azimuth = (viewport_agnostic_point.x - 0.5) * 2.0 / self.hfov_half_cot;
+        let relative_azimuth = Angle::from_radians(tan_relative_azimuth.atan());
+        let azimuth = (self.azimuth + relative_azimuth).as_radians();
+        let vertical_angle = (self.vfov * (viewport_agnostic_point.y - 0.5) + self.vertical_angle).as_radians();
+
+        // `translate` measures the vertical angle against the horizontal distance shortened by the relative azimuth
+        let (sin_vertical, cos_vertical) = vertical_angle.sin_cos();
+        let direction = Vector3d {
+            x: cos_vertical * azimuth.cos(),
+            y: sin_vertical * relative_azimuth.as_radians().cos(),
+            z: cos_vertical * azimuth.sin(),
+        };
+        Ray3d::new(self.position, direction)
+    }
 }
 
 
@@ -142,6 +201,13 @@ impl Viewport {
             y: (viewport_agnostic_point.y * (self.height as f64 - 1.0)).round() as i32,
         }
     }
+
+    pub fn untranslate(self, point: Point) -> BasicPoint<f64> {
+        BasicPoint {
+            x: point.x as f64 / (self.width.max(2) as f64 - 1.0),
+            y: point.y as f64 / (self.height.max(2) as f64 - 1.0),
+        }
+    }
 }
 
 
@@ -202,6 +268,14 @@ impl Renderer<'_> {
         }
     }
     
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    pub fn set_camera(&mut self, camera: Camera) {
+        self.camera = camera;
+    }
+
     pub fn fill_triangle<
         Fill: ParFill + TranslateCoords,
         Constructor: With<Triangle, Output = Fill>
@@ -215,6 +289,10 @@ impl Renderer<'_> {
         }
     }
 
+    pub fn fill_triangle_gouraud(&mut self, tri: Triangle3d, colors: (RGB, RGB, RGB)) {
+        self.fill_triangle(tri, GouraudFill::triangle(colors.0, colors.1, colors.2));
+    }
+
     fn translate_tri(&self, tri: Triangle3d) -> Option<(Triangle, (f64, f64, f64))> {
         let (a, da) = self.translate_point(tri.a);
         let (b, db) = self.translate_point(tri.b);
@@ -227,14 +305,17 @@ impl Renderer<'_> {
         Constructor: With<Triangle, Output = Fill>,
     >(&mut self, par: Par3d, filler_constructor: Constructor) {
         let (tri1, tri2) = par.to_triangles();
-        self
+        let projection = profile::scope("projection");
+        let on_screen = self
             .translate_tri(tri1)
             .and_then(|(tri1_on_screen, depths)| {
                 self.translate_tri(tri2).map(|(tri2_on_screen, _)| {
                     (tri1_on_screen, tri2_on_screen, depths)
                 })
-            })
-            .map(|(tri1_on_screen, tri2_on_screen, depths)| {
+            });
+        drop(projection);
+        let _scope = profile::scope("rasterization");
+        if let Some((tri1_on_screen, tri2_on_screen, depths)) = on_screen {
             let mut filler = ParFillDepthBufferAdapter::new(
                 depths,
                 filler_constructor.with(tri1_on_screen),
@@ -242,9 +323,100 @@ impl Renderer<'_> {
             );
             self.rasterizer.fill_triangle(tri1_on_screen, &mut filler);
             self.rasterizer.fill_triangle(tri2_on_screen, &mut filler);
+        }
+    }
+
+    // Colors are given for `origin`, `origin + vec1`, `origin + vec2` and `origin + vec1 + vec2`
+    pub fn fill_parallelogram_gouraud(&mut self, par: Par3d, colors: (RGB, RGB, RGB, RGB)) {
+        self.fill_parallelogram(par, GouraudFill::parallelogram(colors.0, colors.1, colors.2, colors.3));
+    }
+
+    // Like `fill_parallelogram`, a single filler spans the whole polygon
+    pub fn fill_polygon<
+        Fill: ParFill + TranslateCoords,
+        Constructor: With<Triangle, Output = Fill>,
+    >(&mut self, polygon: &Polygon3d, filler_constructor: Constructor) {
+        let projection = profile::scope("projection");
+        let on_screen: Vec<(Triangle, (f64, f64, f64))> = polygon
+            .triangles()
+            .iter()
+            .filter_map(|&tri| self.translate_tri(tri))
+            .collect();
+        drop(projection);
+        let _scope = profile::scope("rasterization");
+        if let Some(&(reference, depths)) = on_screen.first() {
+            let mut filler = ParFillDepthBufferAdapter::new(
+                depths,
+                filler_constructor.with(reference),
+                &mut self.depth_buffer
+            );
+            for &(tri, _) in &on_screen {
+                self.rasterizer.fill_triangle(tri, &mut filler);
+            }
+        }
+    }
+
+    pub fn draw_mesh<
+        Fill: ParFill + TranslateCoords,
+        Shader: With<MeshTriangle, Output = Fill> + Clone,
+    >(&mut self, mesh: &Mesh, shader: Shader) {
+        self.draw_mesh_transformed(mesh, &Transform::identity(), shader);
+    }
+
+    pub fn draw_mesh_transformed<
+        Fill: ParFill + TranslateCoords,
+        Shader: With<MeshTriangle, Output = Fill> + Clone,
+    >(&mut self, mesh: &Mesh, transform: &Transform, shader: Shader) {
+        // Stages are measured per batch of triangles, as measuring every triangle costs about as much as drawing it
+        let projection = profile::scope("projection");
+        let projected: Vec<(Point, f64)> = mesh
+            .vertices()
+            .iter()
+            .map(|vertex| self.translate_point(transform.apply_point(vertex.position)))
+            .collect();
+        drop(projection);
+
+        let _scope = profile::scope("rasterization");
+
+        for &(i, j, k) in mesh.indices() {
+            let (a, da) = projected[i];
+            let (b, db) = projected[j];
+            let (c, dc) = projected[k];
+            if let Some(triangle_on_screen) = Triangle::try_new(a, b, c) {
+                let mesh_triangle = MeshTriangle {
+                    on_screen: triangle_on_screen,
+                    vertices: (mesh.vertices()[i], mesh.vertices()[j], mesh.vertices()[k]),
+                };
+                let filler = shader.clone().with(mesh_triangle);
+                let mut adapter = ParFillDepthBufferAdapter::new((da, db, dc), filler, &mut self.depth_buffer);
+                self.rasterizer.fill_triangle(triangle_on_screen, &mut adapter);
+            }
+        }
+    }
+
+    pub fn draw_scene(&mut self, scene: &Scene) {
+        scene.visit(|_, node, transform| {
+            for drawable in node.drawables() {
+                drawable.draw(self, transform);
+            }
         });
     }
 
+    pub fn fill_cuboid<
+        Fill: ParFill + TranslateCoords,
+        Constructor: With<Triangle, Output = Fill> + Clone,
+    >(&mut self, cuboid: Cuboid, filler_constructor: Constructor) {
+        for face in cuboid.faces().iter() {
+            self.fill_parallelogram(*face, filler_constructor.clone());
+        }
+    }
+
+    // Overlays are drawn on top of everything rendered so far, ignoring the depth buffer
+    pub fn draw_overlay(&mut self, shape: &impl Rasterize) {
+        let _scope = profile::scope("overlay");
+        shape.rasterize(&mut self.rasterizer);
+    }
+
     fn translate_point(&self, point: Point3d) -> (Point, f64) { 
         let (viewport_agnostic_point, distance) = self.camera.translate(point);
         (self.viewport.translate(viewport_agnostic_point), distance)
@@ -263,6 +435,14 @@ impl Rasterizer<'_> {
         Rasterizer {data, width, height}
     }
 
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
     #[inline]
     pub fn set(&mut self, x: u32, y: u32, value: RGB) {
         self.data[self.index_at(x, y, 0)] = value.b;
@@ -280,7 +460,8 @@ impl Rasterizer<'_> {
         let (a, b, c) = tri.ysort();
         let line_hb = Line::horizontal(b.y);
         let line_ac = Line::from_points(a, c);
-        let split_point = line_hb.intersect(line_ac);
+        // `a` and `c` lie on different sides of the horizontal through `b`, so the split point lies between them
+        let split_point = line_hb.intersect(line_ac).expect("The side of a non-degenerate triangle is not horizontal");
         let horizontal_segment = HorizontalSegment::from_points(b, split_point);
 
         if let Some(glued_top) = GluedTriangle::try_new(horizontal_segment, a) {
@@ -291,6 +472,75 @@ impl Rasterizer<'_> {
         }
     }
 
+    // Polygons that are not simple are not drawn
+    pub fn fill_polygon(&mut self, outer: &[Point], holes: &[Vec<Point>], filler: &mut impl ParFill) {
+        let to_f64 = |point: &Point| BasicPoint {x: point.x as f64, y: point.y as f64};
+        let outer_f64: Vec<_> = outer.iter().map(to_f64).collect();
+        let holes_f64: Vec<Vec<_>> = holes.iter().map(|hole| hole.iter().map(to_f64).collect()).collect();
+        let triangles = match triangulate(&outer_f64, &holes_f64) {
+            Some(triangles) => triangles,
+            None => return,
+        };
+
+        let points: Vec<Point> = outer.iter().chain(holes.iter().flatten()).copied().collect();
+        for (a, b, c) in triangles {
+            if let Some(tri) = Triangle::try_new(points[a], points[b], points[c]) {
+                self.fill_triangle(tri, filler);
+            }
+        }
+    }
+
+    // Scanline fill of pixel centres inside the closed subpaths according to the fill rule
+    pub fn fill_path(&mut self, subpaths: &[Vec<BasicPoint<f64>>], rule: FillRule, filler: &mut impl ParFill) {
+        let edges: Vec<(BasicPoint<f64>, BasicPoint<f64>)> = subpaths
+            .iter()
+            .filter(|subpath| subpath.len() > 2)
+            .flat_map(|subpath| subpath.iter().copied().zip(subpath.iter().copied().cycle().skip(1)))
+            .filter(|(p, q)| p.y != q.y)
+            .collect();
+        if edges.is_empty() {
+            return;
+        }
+        let min_y = edges.iter().map(|(p, q)| p.y.min(q.y)).fold(f64::INFINITY, f64::min);
+        let max_y = edges.iter().map(|(p, q)| p.y.max(q.y)).fold(f64::NEG_INFINITY, f64::max);
+
+        let first_row = min_y.ceil().max(0.0) as i64;
+        let last_row = max_y.floor().min(self.height as f64 - 1.0) as i64;
+        let mut crossings: Vec<(f64, i32)> = Vec::new();
+        for y in first_row..=last_row {
+            let row = y as f64;
+            crossings.clear();
+            for &(p, q) in &edges {
+                // Half-open in y, so that vertices shared by two edges are counted once
+                if (p.y <= row && row < q.y) || (q.y <= row && row < p.y) {
+                    let x = p.x + (row - p.y) * (q.x - p.x) / (q.y - p.y);
+                    crossings.push((x, if q.y > p.y { 1 } else { -1 }));
+                }
+            }
+            crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+            let mut winding = 0;
+            for pair in crossings.windows(2) {
+                winding += pair[0].1;
+                let inside = match rule {
+                    FillRule::NonZero => winding != 0,
+                    FillRule::EvenOdd => winding % 2 != 0,
+                };
+                if !inside {
+                    continue;
+                }
+                let start = pair[0].0.ceil().max(0.0) as i64;
+                let end = (pair[1].0.ceil() - 1.0).min(self.width as f64 - 1.0) as i64;
+                for x in start..=end {
+                    let point = Point {x: x as i32, y: y as i32};
+                    if filler.should_draw(point) {
+                        self.set(x as u32, y as u32, filler.color(point));
+                    }
+                }
+            }
+        }
+    }
+
     pub fn fill_glued_triangle(&mut self, glued_tri: GluedTriangle, filler: &mut impl ParFill) {
         let mut min = glued_tri.horizontal_segment.y();
         let mut max = glued_tri.free_point.y;
@@ -303,8 +553,9 @@ impl Rasterizer<'_> {
 
         for y in (min.max(0))..=(max.min(self.height as i32 - 1)) {
             let horizontal_line = Line::horizontal(y);
-            let  left_isect = horizontal_line.intersect(left_line);
-            let right_isect = horizontal_line.intersect(right_line);
+            // Neither side passes through the horizontal segment, and rows stay within the triangle's span
+            let  left_isect = horizontal_line.intersect(left_line).expect("The side is not horizontal");
+            let right_isect = horizontal_line.intersect(right_line).expect("The side is not horizontal");
 
             for x in (left_isect.x.max(0))..=(right_isect.x.min(self.width as i32 - 1)) {
                 let point = Point {x, y};
@@ -327,6 +578,13 @@ pub trait Rasterize {
 }
 
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FillRule {
+    NonZero,
+    EvenOdd,
+}
+
+
 pub trait ParFill {
     fn color(&self, point: Point) -> RGB;
     fn should_draw(&mut self, _point: Point) -> bool {
@@ -335,27 +593,39 @@ pub trait ParFill {
 }
 
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SolidFill(pub RGB);
+
+impl ParFill for SolidFill {
+    fn color(&self, _point: Point) -> RGB {
+        self.0
+    }
+}
+
+
 pub trait TranslateCoords {
     fn translate_coords(&self, point: Point) -> BasicPoint<f64>;
 }
 
 
 pub struct CoordsTranslator {
-    origin: Point,
+    origin: BasicPoint<f64>,
     basis: Basis<f64>,
 }
 
+// Points are converted before they are subtracted, as their differences may not fit into `i32`
 impl CoordsTranslator {
     pub fn new(tri: Triangle) -> Self {
         CoordsTranslator {
-            origin: tri.a,
+            origin: tri.a.map(&f64::from),
             basis: CoordsTranslator::triangle_to_basis(tri),
         }
     }
 
     fn triangle_to_basis(tri: Triangle) -> Basis<f64> {
-        let u = (tri.b - tri.a).map(&|x| x as f64);
-        let v = (tri.c - tri.a).map(&|x| x as f64);
+        let a = tri.a.map(&f64::from);
+        let u = tri.b.map(&f64::from) - a;
+        let v = tri.c.map(&f64::from) - a;
         let matrix = Matrix2d::from_columns(u.into(), v.into());
         Basis::new(matrix)
     }
@@ -363,7 +633,62 @@ impl CoordsTranslator {
 
 impl TranslateCoords for CoordsTranslator {
     fn translate_coords(&self, point: Point) -> BasicPoint<f64> {
-        BasicPoint::from(self.basis.coords_of((point - self.origin).map(&|x| x as f64)))
+        BasicPoint::from(self.basis.coords_of(point.map(&f64::from) - self.origin))
+    }
+}
+
+
+#[derive(Debug, Clone, Copy)]
+pub struct GouraudFill {
+    corners: [[f64; 3]; 4],
+}
+
+impl GouraudFill {
+    pub fn triangle(a: RGB, b: RGB, c: RGB) -> GouraudFill {
+        // The missing corner is extrapolated, so bilinear interpolation degenerates into a linear one
+        let (a, b, c) = (a.components(), b.components(), c.components());
+        let d = [b[0] + c[0] - a[0], b[1] + c[1] - a[1], b[2] + c[2] - a[2]];
+        GouraudFill {corners: [a, b, c, d]}
+    }
+
+    pub fn parallelogram(origin: RGB, end1: RGB, end2: RGB, opposite: RGB) -> GouraudFill {
+        GouraudFill {corners: [origin.components(), end1.components(), end2.components(), opposite.components()]}
+    }
+}
+
+impl With<Triangle> for GouraudFill {
+    type Output = GouraudFiller;
+
+    fn with(self, tri: Triangle) -> GouraudFiller {
+        GouraudFiller {coord_converter: CoordsTranslator::new(tri), corners: self.corners}
+    }
+}
+
+
+pub struct GouraudFiller {
+    coord_converter: CoordsTranslator,
+    corners: [[f64; 3]; 4],
+}
+
+impl TranslateCoords for GouraudFiller {
+    fn translate_coords(&self, point: Point) -> BasicPoint<f64> {
+        self.coord_converter.translate_coords(point)
+    }
+}
+
+impl ParFill for GouraudFiller {
+    fn color(&self, point: Point) -> RGB {
+        let BasicPoint {x: u, y: v} = self.translate_coords(point);
+        let u = u.max(0.0).min(1.0);
+        let v = v.max(0.0).min(1.0);
+        let weights = [(1.0 - u) * (1.0 - v), u * (1.0 - v), (1.0 - u) * v, u * v];
+        let mut components = [0.0; 3];
+        for (corner, weight) in self.corners.iter().zip(weights.iter()) {
+            for (component, value) in components.iter_mut().zip(corner.iter()) {
+                *component += value * weight;
+            }
+        }
+        RGB::from_components(components)
     }
 }
 
@@ -406,3 +731,68 @@ impl<Filler: ParFill + TranslateCoords> ParFill for ParFillDepthBufferAdapter<'_
         self.depth_buffer.try_update(point.x as u32, point.y as u32, self.get_depth(point) as f32)
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Rasterizes the subpaths into a small buffer and returns which pixels were filled, row by row
+    fn fill(width: u32, height: u32, subpaths: &[Vec<(f64, f64)>], rule: FillRule) -> Vec<String> {
+        let subpaths: Vec<Vec<BasicPoint<f64>>> = subpaths
+            .iter()
+            .map(|subpath| subpath.iter().map(|&point| BasicPoint::from(point)).collect())
+            .collect();
+        let mut data = vec![0; (width * height * 4) as usize];
+        let mut rasterizer = Rasterizer::new(&mut data, width, height);
+        rasterizer.fill_path(&subpaths, rule, &mut SolidFill(RGB::new(255, 255, 255)));
+        data.chunks(width as usize * 4)
+            .map(|row| row.chunks(4).map(|pixel| if pixel[2] == 255 { '#' } else { '.' }).collect())
+            .collect()
+    }
+
+    fn square(min: f64, max: f64) -> Vec<(f64, f64)> {
+        vec![(min, min), (max, min), (max, max), (min, max)]
+    }
+
+    #[test]
+    fn fills_pixel_centres_inside_the_path() {
+        let rows = fill(5, 5, &[square(1.0, 3.0)], FillRule::NonZero);
+        assert_eq!(rows, [".....", ".##..", ".##..", ".....", "....."]);
+    }
+
+    #[test]
+    fn fill_rules_differ_for_nested_subpaths() {
+        let subpaths = [square(0.0, 5.0), square(2.0, 3.0)];
+        assert_eq!(fill(5, 5, &subpaths, FillRule::NonZero), ["#####"; 5]);
+        assert_eq!(fill(5, 5, &subpaths, FillRule::EvenOdd), ["#####", "#####", "##.##", "#####", "#####"]);
+    }
+
+    #[test]
+    fn opposite_windings_cancel_out() {
+        let mut hole = square(2.0, 3.0);
+        hole.reverse();
+        assert_eq!(fill(5, 5, &[square(0.0, 5.0), hole], FillRule::NonZero)[2], "##.##");
+    }
+
+    #[test]
+    fn self_intersecting_path_follows_the_fill_rule() {
+        // A pentagram, whose centre is wound twice
+        let star: Vec<(f64, f64)> = (0..5)
+            .map(|index| {
+                let angle = (index * 2 % 5) as f64 * 2.0 * std::f64::consts::PI / 5.0;
+                (10.0 + 9.0 * angle.sin(), 10.0 - 9.0 * angle.cos())
+            })
+            .collect();
+        let centre = |rule| fill(20, 20, std::slice::from_ref(&star), rule)[10].as_bytes()[10];
+        assert_eq!(centre(FillRule::NonZero), b'#');
+        assert_eq!(centre(FillRule::EvenOdd), b'.');
+    }
+
+    #[test]
+    fn clips_to_the_buffer() {
+        let rows = fill(3, 3, &[square(-10.0, 10.0)], FillRule::NonZero);
+        assert_eq!(rows, ["###"; 3]);
+        assert_eq!(fill(3, 3, &[square(5.0, 10.0)], FillRule::NonZero), ["..."; 3]);
+    }
+}
@@ -0,0 +1,287 @@
+use crate::geometry::{
+    intersect_ray_aabb,
+    intersect_ray_triangle,
+    Aabb,
+    Par3d,
+    Point3d,
+    Ray3d,
+    Triangle,
+    Triangle3d,
+    Vector3d,
+};
+use crate::linalg::{Quaternion, Transform};
+use crate::mesh::{Mesh, MeshTriangle};
+use crate::polygon::Polygon3d;
+use crate::render::{ParFill, Render, Renderer, TranslateCoords};
+use crate::with::With;
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NodeTransform {
+    pub translation: Vector3d,
+    pub rotation: Quaternion,
+    pub scale: Vector3d,
+}
+
+impl NodeTransform {
+    pub fn identity() -> NodeTransform {
+        NodeTransform {
+            translation: Vector3d {x: 0.0, y: 0.0, z: 0.0},
+            rotation: Quaternion::identity(),
+            scale: Vector3d {x: 1.0, y: 1.0, z: 1.0},
+        }
+    }
+
+    pub fn from_translation(translation: Vector3d) -> NodeTransform {
+        NodeTransform {translation, ..NodeTransform::identity()}
+    }
+
+    pub fn with_rotation(self, rotation: Quaternion) -> NodeTransform {
+        NodeTransform {rotation, ..self}
+    }
+
+    pub fn with_scale(self, scale: Vector3d) -> NodeTransform {
+        NodeTransform {scale, ..self}
+    }
+
+    pub fn to_transform(self) -> Transform {
+        Transform::from_trs(self.translation, self.rotation, self.scale)
+    }
+}
+
+impl Default for NodeTransform {
+    fn default() -> NodeTransform {
+        NodeTransform::identity()
+    }
+}
+
+
+pub trait Drawable {
+    fn draw(&self, renderer: &mut Renderer<'_>, transform: &Transform);
+
+    // The distance along the ray to the nearest hit of the drawable placed with `transform`
+    fn intersect_ray(&self, ray: &Ray3d, transform: &Transform) -> Option<f64>;
+}
+
+
+pub struct MeshDrawable<Shader> {
+    pub mesh: Mesh,
+    pub shader: Shader,
+}
+
+impl<
+    Fill: ParFill + TranslateCoords,
+    Shader: With<MeshTriangle, Output = Fill> + Clone,
+> Drawable for MeshDrawable<Shader> {
+    fn draw(&self, renderer: &mut Renderer<'_>, transform: &Transform) {
+        renderer.draw_mesh_transformed(&self.mesh, transform, self.shader.clone());
+    }
+
+    fn intersect_ray(&self, ray: &Ray3d, transform: &Transform) -> Option<f64> {
+        let positions: Vec<Point3d> = self
+            .mesh
+            .vertices()
+            .iter()
+            .map(|vertex| transform.apply_point(vertex.position))
+            .collect();
+        // Cheap rejection of rays missing the whole mesh
+        intersect_ray_aabb(ray, &Aabb::from_points(positions.iter().copied())?)?;
+        self.mesh
+            .indices()
+            .iter()
+            .filter_map(|&(i, j, k)| Triangle3d::try_new(positions[i], positions[j], positions[k]))
+            .filter_map(|tri| intersect_ray_triangle(ray, &tri))
+            .map(|hit| hit.distance)
+            .min_by(f64::total_cmp)
+    }
+}
+
+
+pub struct ParDrawable<Constructor> {
+    pub par: Par3d,
+    pub filler_constructor: Constructor,
+}
+
+impl<
+    Fill: ParFill + TranslateCoords,
+    Constructor: With<Triangle, Output = Fill> + Clone,
+> Drawable for ParDrawable<Constructor> {
+    fn draw(&self, renderer: &mut Renderer<'_>, transform: &Transform) {
+        // Parallelograms squashed by a zero scale are not drawn
+        if let Some(par) = transform.apply_par(self.par) {
+            renderer.fill_parallelogram(par, self.filler_constructor.clone());
+        }
+    }
+
+    fn intersect_ray(&self, ray: &Ray3d, transform: &Transform) -> Option<f64> {
+        let (tri1, tri2) = transform.apply_par(self.par)?.to_triangles();
+        let hits = [intersect_ray_triangle(ray, &tri1), intersect_ray_triangle(ray, &tri2)];
+        hits.iter().flatten().map(|hit| hit.distance).min_by(f64::total_cmp)
+    }
+}
+
+
+pub struct PolygonDrawable<Constructor> {
+    pub polygon: Polygon3d,
+    pub filler_constructor: Constructor,
+}
+
+impl<
+    Fill: ParFill + TranslateCoords,
+    Constructor: With<Triangle, Output = Fill> + Clone,
+> Drawable for PolygonDrawable<Constructor> {
+    fn draw(&self, renderer: &mut Renderer<'_>, transform: &Transform) {
+        if let Some(polygon) = self.polygon.transformed(transform) {
+            renderer.fill_polygon(&polygon, self.filler_constructor.clone());
+        }
+    }
+
+    fn intersect_ray(&self, ray: &Ray3d, transform: &Transform) -> Option<f64> {
+        self.polygon
+            .triangles()
+            .iter()
+            .filter_map(|&tri| transform.apply_triangle(tri))
+            .filter_map(|tri| intersect_ray_triangle(ray, &tri))
+            .map(|hit| hit.distance)
+            .min_by(f64::total_cmp)
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+
+// The nearest drawable under a ray: its node, its index among the node's drawables, and the world-space hit point
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PickHit {
+    pub node: NodeId,
+    pub drawable: usize,
+    pub distance: f64,
+    pub point: Point3d,
+}
+
+
+pub struct SceneNode {
+    pub name: String,
+    pub transform: NodeTransform,
+    pub visible: bool,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    drawables: Vec<Box<dyn Drawable>>,
+}
+
+impl SceneNode {
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    pub fn children(&self) -> &[NodeId] {
+        &self.children
+    }
+
+    pub fn drawables(&self) -> &[Box<dyn Drawable>] {
+        &self.drawables
+    }
+}
+
+
+#[derive(Default)]
+pub struct Scene {
+    nodes: Vec<SceneNode>,
+    roots: Vec<NodeId>,
+}
+
+impl Scene {
+    pub fn new() -> Scene {
+        Scene::default()
+    }
+
+    pub fn add_node(&mut self, parent: Option<NodeId>, name: &str, transform: NodeTransform) -> NodeId {
+        let id = NodeId(self.nodes.len());
+        self.nodes.push(SceneNode {
+            name: name.to_owned(),
+            transform,
+            visible: true,
+            parent,
+            children: Vec::new(),
+            drawables: Vec::new(),
+        });
+        match parent {
+            Some(parent) => self.nodes[parent.0].children.push(id),
+            None => self.roots.push(id),
+        }
+        id
+    }
+
+    pub fn attach(&mut self, node: NodeId, drawable: impl Drawable + 'static) {
+        self.nodes[node.0].drawables.push(Box::new(drawable));
+    }
+
+    pub fn node(&self, id: NodeId) -> &SceneNode {
+        &self.nodes[id.0]
+    }
+
+    pub fn node_mut(&mut self, id: NodeId) -> &mut SceneNode {
+        &mut self.nodes[id.0]
+    }
+
+    pub fn roots(&self) -> &[NodeId] {
+        &self.roots
+    }
+
+    pub fn find(&self, name: &str) -> Option<NodeId> {
+        self.nodes.iter().position(|node| node.name == name).map(NodeId)
+    }
+
+    pub fn world_transform(&self, id: NodeId) -> Transform {
+        let node = &self.nodes[id.0];
+        let local = node.transform.to_transform();
+        match node.parent {
+            Some(parent) => self.world_transform(parent) * local,
+            None => local,
+        }
+    }
+
+    // Visits visible nodes depth-first, passing their composed world transforms
+    pub fn visit(&self, mut visitor: impl FnMut(NodeId, &SceneNode, &Transform)) {
+        let mut stack: Vec<(NodeId, Transform)> = self
+            .roots
+            .iter()
+            .rev()
+            .map(|&root| (root, Transform::identity()))
+            .collect();
+        while let Some((id, parent_transform)) = stack.pop() {
+            let node = &self.nodes[id.0];
+            if !node.visible {
+                continue;
+            }
+            let transform = parent_transform * node.transform.to_transform();
+            visitor(id, node, &transform);
+            stack.extend(node.children.iter().rev().map(|&child| (child, transform)));
+        }
+    }
+
+    // Hidden nodes cannot be picked
+    pub fn pick(&self, ray: &Ray3d) -> Option<PickHit> {
+        let mut nearest: Option<PickHit> = None;
+        self.visit(|id, node, transform| {
+            for (index, drawable) in node.drawables.iter().enumerate() {
+                let distance = match drawable.intersect_ray(ray, transform) {
+                    Some(distance) => distance,
+                    None => continue,
+                };
+                if nearest.is_none_or(|hit| distance < hit.distance) {
+                    nearest = Some(PickHit {node: id, drawable: index, distance, point: ray.point_at(distance)});
+                }
+            }
+        });
+        nearest
+    }
+}
+
+impl Render for Scene {
+    fn render<'a>(&self, renderer: &mut Renderer<'a>) {
+        renderer.draw_scene(self);
+    }
+}
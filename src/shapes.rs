@@ -0,0 +1,232 @@
+use crate::geometry::{BasicPoint, Cross, Cuboid, Dot, Normalize, Point3d, Vector3d};
+use crate::mesh::{Mesh, Vertex};
+
+use core::f64::consts::PI;
+
+
+// All generators put their shapes around the Y axis, which points down in the renderer's coordinates.
+// Vertices get normals and UV coordinates; seams duplicate vertices so that UVs can wrap around.
+
+
+pub fn cuboid(cuboid: &Cuboid) -> Mesh {
+    let mut mesh = Mesh::new();
+    let (u, v, w) = cuboid.edges();
+    let center = cuboid.origin() + (u + v + w) * 0.5;
+    for face in cuboid.faces().iter() {
+        let (vec1, vec2) = face.vectors();
+        let outwards = (face.origin() + (vec1 + vec2) * 0.5) - center;
+        let normal = vec1.cross(&vec2).normalized().expect("Cuboid faces are not degenerate");
+        let normal = if normal.dot(&outwards) < 0.0 { -normal } else { normal };
+        push_quad(&mut mesh, face.origin(), vec1, vec2, normal);
+    }
+    mesh
+}
+
+
+pub fn sphere(center: Point3d, radius: f64, segments: usize, rings: usize) -> Mesh {
+    assert!(segments >= 3 && rings >= 2, "A sphere needs at least 3 segments and 2 rings");
+    let mut mesh = Mesh::new();
+    for ring in 0..=rings {
+        let v = ring as f64 / rings as f64;
+        let polar = v * PI;
+        for segment in 0..=segments {
+            let u = segment as f64 / segments as f64;
+            let azimuth = u * 2.0 * PI;
+            let normal = Vector3d {x: polar.sin() * azimuth.cos(), y: -polar.cos(), z: polar.sin() * azimuth.sin()};
+            mesh.push_vertex(Vertex {
+                normal: Some(normal),
+                uv: Some(BasicPoint {x: u, y: v}),
+                ..Vertex::new(center + normal * radius)
+            });
+        }
+    }
+
+    let row = segments + 1;
+    for ring in 0..rings {
+        for segment in 0..segments {
+            let a = ring * row + segment;
+            let b = a + 1;
+            let c = a + row;
+            let d = c + 1;
+            // Pole rows collapse into points, so only one triangle of each quad there is not degenerate
+            if ring != 0 {
+                mesh.push_triangle(a, b, c);
+            }
+            if ring != rings - 1 {
+                mesh.push_triangle(b, d, c);
+            }
+        }
+    }
+    mesh
+}
+
+
+pub fn cylinder(base_center: Point3d, radius: f64, height: f64, segments: usize, capped: bool) -> Mesh {
+    frustum(base_center, radius, radius, height, segments, capped)
+}
+
+
+pub fn cone(base_center: Point3d, radius: f64, height: f64, segments: usize, capped: bool) -> Mesh {
+    frustum(base_center, radius, 0.0, height, segments, capped)
+}
+
+
+// A truncated cone going from `base_center` along the Y axis
+pub fn frustum(
+    base_center: Point3d,
+    bottom_radius: f64,
+    top_radius: f64,
+    height: f64,
+    segments: usize,
+    capped: bool
+) -> Mesh {
+    assert!(segments >= 3, "A round shape needs at least 3 segments");
+    assert!(height != 0.0, "A frustum needs a non-zero height");
+    let mut mesh = Mesh::new();
+    let top_center = base_center + Vector3d {x: 0.0, y: height, z: 0.0};
+    let slope = (bottom_radius - top_radius) / height;
+
+    for segment in 0..=segments {
+        let u = segment as f64 / segments as f64;
+        let azimuth = u * 2.0 * PI;
+        let direction = Vector3d {x: azimuth.cos(), y: 0.0, z: azimuth.sin()};
+        let normal = Vector3d {x: direction.x, y: slope, z: direction.z};
+        let normal = normal.normalized().expect("The side normal has a unit horizontal component");
+        for &(center, radius, v) in &[(base_center, bottom_radius, 1.0), (top_center, top_radius, 0.0)] {
+            mesh.push_vertex(Vertex {
+                normal: Some(normal),
+                uv: Some(BasicPoint {x: u, y: v}),
+                ..Vertex::new(center + direction * radius)
+            });
+        }
+    }
+    for segment in 0..segments {
+        let bottom = 2 * segment;
+        if bottom_radius != 0.0 {
+            mesh.push_triangle(bottom, bottom + 2, bottom + 1);
+        }
+        if top_radius != 0.0 {
+            mesh.push_triangle(bottom + 1, bottom + 2, bottom + 3);
+        }
+    }
+
+    if capped {
+        let down = Vector3d {x: 0.0, y: -1.0, z: 0.0};
+        let up = Vector3d {x: 0.0, y: 1.0, z: 0.0};
+        for &(center, radius, normal) in &[(base_center, bottom_radius, down), (top_center, top_radius, up)] {
+            if radius != 0.0 {
+                push_disc(&mut mesh, center, radius, normal, segments);
+            }
+        }
+    }
+    mesh
+}
+
+
+pub fn torus(
+    center: Point3d,
+    major_radius: f64,
+    minor_radius: f64,
+    major_segments: usize,
+    minor_segments: usize
+) -> Mesh {
+    assert!(major_segments >= 3 && minor_segments >= 3, "A torus needs at least 3 segments in each direction");
+    let mut mesh = Mesh::new();
+    for major in 0..=major_segments {
+        let u = major as f64 / major_segments as f64;
+        let theta = u * 2.0 * PI;
+        let direction = Vector3d {x: theta.cos(), y: 0.0, z: theta.sin()};
+        let ring_center = center + direction * major_radius;
+        for minor in 0..=minor_segments {
+            let v = minor as f64 / minor_segments as f64;
+            let phi = v * 2.0 * PI;
+            let normal = Vector3d {x: direction.x * phi.cos(), y: phi.sin(), z: direction.z * phi.cos()};
+            mesh.push_vertex(Vertex {
+                normal: Some(normal),
+                uv: Some(BasicPoint {x: u, y: v}),
+                ..Vertex::new(ring_center + normal * minor_radius)
+            });
+        }
+    }
+
+    let row = minor_segments + 1;
+    for major in 0..major_segments {
+        for minor in 0..minor_segments {
+            let a = major * row + minor;
+            let b = a + 1;
+            let c = a + row;
+            let d = c + 1;
+            mesh.push_triangle(a, c, b);
+            mesh.push_triangle(b, c, d);
+        }
+    }
+    mesh
+}
+
+
+// A parallelogram split into a grid of `subdivisions_u` by `subdivisions_v` cells
+pub fn plane(origin: Point3d, vec1: Vector3d, vec2: Vector3d, subdivisions_u: usize, subdivisions_v: usize) -> Mesh {
+    assert!(subdivisions_u >= 1 && subdivisions_v >= 1, "A plane needs at least one subdivision in each direction");
+    let mut mesh = Mesh::new();
+    let normal = vec1.cross(&vec2).normalized().expect("Plane vectors must not be parallel");
+    for j in 0..=subdivisions_v {
+        let v = j as f64 / subdivisions_v as f64;
+        for i in 0..=subdivisions_u {
+            let u = i as f64 / subdivisions_u as f64;
+            mesh.push_vertex(Vertex {
+                normal: Some(normal),
+                uv: Some(BasicPoint {x: u, y: v}),
+                ..Vertex::new(origin + vec1 * u + vec2 * v)
+            });
+        }
+    }
+
+    let row = subdivisions_u + 1;
+    for j in 0..subdivisions_v {
+        for i in 0..subdivisions_u {
+            let a = j * row + i;
+            mesh.push_triangle(a, a + 1, a + row);
+            mesh.push_triangle(a + 1, a + row + 1, a + row);
+        }
+    }
+    mesh
+}
+
+
+fn push_quad(mesh: &mut Mesh, origin: Point3d, vec1: Vector3d, vec2: Vector3d, normal: Vector3d) {
+    let corners = [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)];
+    let start = mesh.vertices().len();
+    for &(u, v) in &corners {
+        mesh.push_vertex(Vertex {
+            normal: Some(normal),
+            uv: Some(BasicPoint {x: u, y: v}),
+            ..Vertex::new(origin + vec1 * u + vec2 * v)
+        });
+    }
+    mesh.push_triangle(start, start + 1, start + 2);
+    mesh.push_triangle(start + 3, start + 2, start + 1);
+}
+
+
+fn push_disc(mesh: &mut Mesh, center: Point3d, radius: f64, normal: Vector3d, segments: usize) {
+    let start = mesh.push_vertex(Vertex {
+        normal: Some(normal),
+        uv: Some(BasicPoint {x: 0.5, y: 0.5}),
+        ..Vertex::new(center)
+    });
+    for segment in 0..segments {
+        let azimuth = segment as f64 / segments as f64 * 2.0 * PI;
+        let (sin, cos) = azimuth.sin_cos();
+        mesh.push_vertex(Vertex {
+            normal: Some(normal),
+            uv: Some(BasicPoint {x: 0.5 + 0.5 * cos, y: 0.5 + 0.5 * sin}),
+            ..Vertex::new(center + Vector3d {x: radius * cos, y: 0.0, z: radius * sin})
+        });
+    }
+    for segment in 0..segments {
+        let current = start + 1 + segment;
+        let next = start + 1 + (segment + 1) % segments;
+        mesh.push_triangle(start, current, next);
+    }
+}
+
@@ -0,0 +1,130 @@
+use crate::geometry::{BasicPoint, Point};
+use crate::mesh::MeshTriangle;
+use crate::render::{RGB, CoordsTranslator, GouraudFill, GouraudFiller, ParFill, TranslateCoords};
+use crate::with::With;
+use super::SdlError;
+
+use sdl2::image::{ImageRWops, LoadSurface};
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::rwops::RWops;
+use sdl2::surface::Surface;
+use std::path::Path;
+use std::rc::Rc;
+
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Texture {
+    width: u32,
+    height: u32,
+    pixels: Vec<RGB>,
+}
+
+impl Texture {
+    pub fn new(width: u32, height: u32, pixels: Vec<RGB>) -> Texture {
+        assert_eq!(pixels.len(), width as usize * height as usize, "Texture size does not match its dimensions");
+        assert!(width > 0 && height > 0, "Texture must not be empty");
+        Texture {width, height, pixels}
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Texture, SdlError> {
+        Texture::from_surface(Surface::from_file(path)?)
+    }
+
+    // Decodes an image in any format supported by SDL_image (PNG, JPEG, ...)
+    pub fn from_encoded(data: &[u8]) -> Result<Texture, SdlError> {
+        let rwops = RWops::from_bytes(data)?;
+        Texture::from_surface(rwops.load()?)
+    }
+
+    fn from_surface(surface: Surface) -> Result<Texture, SdlError> {
+        let surface = surface.convert_format(PixelFormatEnum::RGB24)?;
+        let width = surface.width();
+        let height = surface.height();
+        let pitch = surface.pitch() as usize;
+        let pixels = surface.with_lock(|data| {
+            let mut pixels = Vec::with_capacity(width as usize * height as usize);
+            for y in 0..height as usize {
+                let row = &data[y * pitch..];
+                for x in 0..width as usize {
+                    pixels.push(RGB::new(row[3 * x], row[3 * x + 1], row[3 * x + 2]));
+                }
+            }
+            pixels
+        });
+        if pixels.is_empty() {
+            return Err(SdlError::new(String::from("Texture image is empty")));
+        }
+        Ok(Texture {width, height, pixels})
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    // Nearest-neighbour sampling with repeat wrapping, the UV origin is the top left corner as in glTF
+    pub fn sample(&self, uv: BasicPoint<f64>) -> RGB {
+        let x = (uv.x.rem_euclid(1.0) * self.width as f64) as u32;
+        let y = (uv.y.rem_euclid(1.0) * self.height as f64) as u32;
+        let x = x.min(self.width - 1);
+        let y = y.min(self.height - 1);
+        self.pixels[y as usize * self.width as usize + x as usize]
+    }
+}
+
+
+// Textures are shared, so that shaders can be cloned for every triangle and kept in a scene
+#[derive(Debug, Clone)]
+pub struct TextureShader {
+    texture: Rc<Texture>,
+}
+
+impl TextureShader {
+    pub fn new(texture: Rc<Texture>) -> TextureShader {
+        TextureShader {texture}
+    }
+}
+
+impl With<MeshTriangle> for TextureShader {
+    type Output = TextureFiller;
+
+    fn with(self, tri: MeshTriangle) -> TextureFiller {
+        let (a, b, c) = tri.vertices;
+        let origin = BasicPoint {x: 0.0, y: 0.0};
+        TextureFiller {
+            coord_converter: CoordsTranslator::new(tri.on_screen),
+            uvs: (a.uv.unwrap_or(origin), b.uv.unwrap_or(origin), c.uv.unwrap_or(origin)),
+            tint: GouraudFill::triangle(a.color, b.color, c.color).with(tri.on_screen),
+            texture: self.texture,
+        }
+    }
+}
+
+
+pub struct TextureFiller {
+    coord_converter: CoordsTranslator,
+    uvs: (BasicPoint<f64>, BasicPoint<f64>, BasicPoint<f64>),
+    tint: GouraudFiller,
+    texture: Rc<Texture>,
+}
+
+impl TranslateCoords for TextureFiller {
+    fn translate_coords(&self, point: Point) -> BasicPoint<f64> {
+        self.coord_converter.translate_coords(point)
+    }
+}
+
+impl ParFill for TextureFiller {
+    fn color(&self, point: Point) -> RGB {
+        let BasicPoint {x: u, y: v} = self.translate_coords(point);
+        let (a, b, c) = self.uvs;
+        let uv = BasicPoint {
+            x: a.x + u * (b.x - a.x) + v * (c.x - a.x),
+            y: a.y + u * (b.y - a.y) + v * (c.y - a.y),
+        };
+        self.texture.sample(uv).modulate(self.tint.color(point))
+    }
+}
@@ -0,0 +1,303 @@
+use crate::geometry::{Par3d, Point3d, Vector3d};
+
+use std::collections::HashMap;
+
+
+pub const CHUNK_SIZE: usize = 16;
+const CHUNK_VOLUME: usize = CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE;
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct BlockId(pub u16);
+
+impl BlockId {
+    pub const AIR: BlockId = BlockId(0);
+
+    pub fn is_air(self) -> bool {
+        self == BlockId::AIR
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BlockPosition {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl BlockPosition {
+    pub fn new(x: i32, y: i32, z: i32) -> BlockPosition {
+        BlockPosition {x, y, z}
+    }
+
+    pub fn chunk(self) -> ChunkPosition {
+        let size = CHUNK_SIZE as i32;
+        ChunkPosition {x: self.x.div_euclid(size), y: self.y.div_euclid(size), z: self.z.div_euclid(size)}
+    }
+
+    fn local(self) -> (usize, usize, usize) {
+        let size = CHUNK_SIZE as i32;
+        (self.x.rem_euclid(size) as usize, self.y.rem_euclid(size) as usize, self.z.rem_euclid(size) as usize)
+    }
+
+    fn coordinates(self) -> [i32; 3] {
+        [self.x, self.y, self.z]
+    }
+
+    fn from_coordinates(coordinates: [i32; 3]) -> BlockPosition {
+        BlockPosition {x: coordinates[0], y: coordinates[1], z: coordinates[2]}
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ChunkPosition {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl ChunkPosition {
+    pub fn origin(self) -> BlockPosition {
+        let size = CHUNK_SIZE as i32;
+        BlockPosition {x: self.x * size, y: self.y * size, z: self.z * size}
+    }
+}
+
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct Chunk {
+    blocks: Box<[BlockId]>,
+    solid_count: usize,
+}
+
+impl Chunk {
+    pub fn new() -> Chunk {
+        Chunk {blocks: vec![BlockId::AIR; CHUNK_VOLUME].into_boxed_slice(), solid_count: 0}
+    }
+
+    pub fn get(&self, x: usize, y: usize, z: usize) -> BlockId {
+        self.blocks[Chunk::index(x, y, z)]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, z: usize, block: BlockId) {
+        let slot = &mut self.blocks[Chunk::index(x, y, z)];
+        match (slot.is_air(), block.is_air()) {
+            (true, false) => self.solid_count += 1,
+            (false, true) => self.solid_count -= 1,
+            _ => {},
+        }
+        *slot = block;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.solid_count == 0
+    }
+
+    fn index(x: usize, y: usize, z: usize) -> usize {
+        assert!(x < CHUNK_SIZE && y < CHUNK_SIZE && z < CHUNK_SIZE, "Block coordinates are outside of the chunk");
+        (z * CHUNK_SIZE + y) * CHUNK_SIZE + x
+    }
+}
+
+impl Default for Chunk {
+    fn default() -> Chunk {
+        Chunk::new()
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FaceDirection {
+    NegativeX,
+    PositiveX,
+    NegativeY,
+    PositiveY,
+    NegativeZ,
+    PositiveZ,
+}
+
+impl FaceDirection {
+    pub const ALL: [FaceDirection; 6] = [
+        FaceDirection::NegativeX,
+        FaceDirection::PositiveX,
+        FaceDirection::NegativeY,
+        FaceDirection::PositiveY,
+        FaceDirection::NegativeZ,
+        FaceDirection::PositiveZ,
+    ];
+
+    pub fn normal(self) -> Vector3d {
+        let mut coordinates = [0.0; 3];
+        coordinates[self.axis()] = self.sign() as f64;
+        Vector3d {x: coordinates[0], y: coordinates[1], z: coordinates[2]}
+    }
+
+    fn axis(self) -> usize {
+        match self {
+            FaceDirection::NegativeX | FaceDirection::PositiveX => 0,
+            FaceDirection::NegativeY | FaceDirection::PositiveY => 1,
+            FaceDirection::NegativeZ | FaceDirection::PositiveZ => 2,
+        }
+    }
+
+    fn sign(self) -> i32 {
+        match self {
+            FaceDirection::NegativeX | FaceDirection::NegativeY | FaceDirection::NegativeZ => -1,
+            FaceDirection::PositiveX | FaceDirection::PositiveY | FaceDirection::PositiveZ => 1,
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VoxelQuad {
+    pub face: Par3d,
+    pub block: BlockId,
+    pub direction: FaceDirection,
+}
+
+
+pub struct VoxelWorld {
+    chunks: HashMap<ChunkPosition, Chunk>,
+    block_size: f64,
+}
+
+impl VoxelWorld {
+    pub fn new(block_size: f64) -> VoxelWorld {
+        assert!(block_size > 0.0 && block_size.is_finite(), "Block size must be finite and positive");
+        VoxelWorld {chunks: HashMap::new(), block_size}
+    }
+
+    pub fn block_size(&self) -> f64 {
+        self.block_size
+    }
+
+    pub fn get_block(&self, position: BlockPosition) -> BlockId {
+        let (x, y, z) = position.local();
+        self.chunks.get(&position.chunk()).map_or(BlockId::AIR, |chunk| chunk.get(x, y, z))
+    }
+
+    pub fn set_block(&mut self, position: BlockPosition, block: BlockId) {
+        let (x, y, z) = position.local();
+        let chunk_position = position.chunk();
+        if block.is_air() {
+            if let Some(chunk) = self.chunks.get_mut(&chunk_position) {
+                chunk.set(x, y, z, block);
+                if chunk.is_empty() {
+                    self.chunks.remove(&chunk_position);
+                }
+            }
+        } else {
+            self.chunks.entry(chunk_position).or_default().set(x, y, z, block);
+        }
+    }
+
+    pub fn chunk(&self, position: ChunkPosition) -> Option<&Chunk> {
+        self.chunks.get(&position)
+    }
+
+    pub fn chunk_positions(&self) -> impl Iterator<Item = ChunkPosition> + '_ {
+        self.chunks.keys().copied()
+    }
+
+    pub fn mesh(&self) -> Vec<VoxelQuad> {
+        self.chunk_positions().flat_map(|position| self.mesh_chunk(position)).collect()
+    }
+
+    // Emits only faces exposed to air; coplanar faces of the same block type are greedily merged
+    pub fn mesh_chunk(&self, position: ChunkPosition) -> Vec<VoxelQuad> {
+        let mut quads = Vec::new();
+        if !self.chunks.contains_key(&position) {
+            return quads;
+        }
+        let origin = position.origin().coordinates();
+
+        for &direction in FaceDirection::ALL.iter() {
+            let axis = direction.axis();
+            let u_axis = (axis + 1) % 3;
+            let v_axis = (axis + 2) % 3;
+
+            for layer in 0..CHUNK_SIZE {
+                let mut mask = [[None; CHUNK_SIZE]; CHUNK_SIZE];
+                for (u, row) in mask.iter_mut().enumerate() {
+                    for (v, cell) in row.iter_mut().enumerate() {
+                        let mut coordinates = origin;
+                        coordinates[axis] += layer as i32;
+                        coordinates[u_axis] += u as i32;
+                        coordinates[v_axis] += v as i32;
+                        let block = self.get_block(BlockPosition::from_coordinates(coordinates));
+                        if block.is_air() {
+                            continue;
+                        }
+                        coordinates[axis] += direction.sign();
+                        if self.get_block(BlockPosition::from_coordinates(coordinates)).is_air() {
+                            *cell = Some(block);
+                        }
+                    }
+                }
+
+                for (u, v, width, height, block) in greedy_rectangles(&mut mask) {
+                    let mut corner = [0.0; 3];
+                    corner[axis] = (origin[axis] + layer as i32 + direction.sign().max(0)) as f64;
+                    corner[u_axis] = (origin[u_axis] + u as i32) as f64;
+                    corner[v_axis] = (origin[v_axis] + v as i32) as f64;
+                    let mut vec1 = [0.0; 3];
+                    vec1[u_axis] = width as f64 * self.block_size;
+                    let mut vec2 = [0.0; 3];
+                    vec2[v_axis] = height as f64 * self.block_size;
+                    let size = self.block_size;
+                    quads.push(VoxelQuad {
+                        face: Par3d::new(
+                            Point3d {x: corner[0] * size, y: corner[1] * size, z: corner[2] * size},
+                            Vector3d {x: vec1[0], y: vec1[1], z: vec1[2]},
+                            Vector3d {x: vec2[0], y: vec2[1], z: vec2[2]},
+                        ),
+                        block,
+                        direction,
+                    });
+                }
+            }
+        }
+        quads
+    }
+}
+
+
+// Splits the mask into maximal rectangles (u, v, width, height, block), clearing it in the process
+fn greedy_rectangles(
+    mask: &mut [[Option<BlockId>; CHUNK_SIZE]; CHUNK_SIZE]
+) -> Vec<(usize, usize, usize, usize, BlockId)> {
+    let mut result = Vec::new();
+    for u in 0..CHUNK_SIZE {
+        let mut v = 0;
+        while v < CHUNK_SIZE {
+            let block = match mask[u][v] {
+                Some(block) => block,
+                None => {
+                    v += 1;
+                    continue;
+                },
+            };
+
+            let mut height = 1;
+            while v + height < CHUNK_SIZE && mask[u][v + height] == Some(block) {
+                height += 1;
+            }
+            let mut width = 1;
+            while u + width < CHUNK_SIZE && mask[u + width][v..v + height].iter().all(|&cell| cell == Some(block)) {
+                width += 1;
+            }
+
+            for row in mask[u..u + width].iter_mut() {
+                for cell in row[v..v + height].iter_mut() {
+                    *cell = None;
+                }
+            }
+            result.push((u, v, width, height, block));
+            v += height;
+        }
+    }
+    result
+}